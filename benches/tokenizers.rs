@@ -0,0 +1,64 @@
+//! Throughput of the tokenizers over the sources in `test/`, run with `cargo bench`
+
+extern crate rust_lexer;
+
+use std::fs::{read_dir, read_to_string};
+use std::time::{Duration, Instant};
+
+use rust_lexer::{StrTokenizer, Tokenizer};
+
+/// Size of the source the tokenizers are run on
+const SIZE: usize = 10 << 20;
+/// Number of runs of each tokenizer, the shortest one is reported
+const RUNS: usize = 20;
+
+/// Time of a run of the function, which returns the number of tokens
+fn time<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+    let start = Instant::now();
+    let tokens = f();
+    (start.elapsed(), tokens)
+}
+
+fn report(name: &str, (best, tokens): (Duration, usize)) {
+    let speed = SIZE as f64 / best.as_secs_f64() / f64::from(1 << 20);
+    println!(
+        "{:<16} {:>8.1} ms {:>8.1} MiB/s {:>10} tokens",
+        name,
+        best.as_secs_f64() * 1000.0,
+        speed,
+        tokens
+    );
+}
+
+fn main() {
+    let mut paths = read_dir(concat!(env!("CARGO_MANIFEST_DIR"), "/test"))
+        .unwrap()
+        .map(|i| i.unwrap().path())
+        .filter(|i| i.extension().and_then(|i| i.to_str()) == Some("rs"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    let sources = paths
+        .iter()
+        .map(|i| read_to_string(i).unwrap())
+        .collect::<String>();
+    let mut src = sources.repeat(SIZE / sources.len() + 1);
+    let mut end = SIZE;
+    while !src.is_char_boundary(end) {
+        end += 1;
+    }
+    src.truncate(end);
+
+    // The runs of the tokenizers alternate, so that both are measured in the same conditions
+    let mut chars = (Duration::MAX, 0);
+    let mut str = (Duration::MAX, 0);
+    for _ in 0..RUNS {
+        chars = chars.min(time(|| Tokenizer::new(src.chars()).count()));
+        str = str.min(time(|| StrTokenizer::new(&src).count()));
+    }
+    report("Tokenizer", chars);
+    report("StrTokenizer", str);
+    println!(
+        "StrTokenizer is {:.1} times faster",
+        chars.0.as_secs_f64() / str.0.as_secs_f64()
+    );
+}
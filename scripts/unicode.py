@@ -0,0 +1,123 @@
+#!/usr/bin/env python3
+"""Generate src/unicode_tables.rs from the Unicode database bundled with Python.
+
+Usage: python3 scripts/unicode.py > src/unicode_tables.rs
+"""
+
+import sys
+import unicodedata
+
+MAX = sys.maxunicode + 1
+
+
+def ranges(predicate):
+    result = []
+    start = None
+    for code in range(MAX):
+        if predicate(code):
+            if start is None:
+                start = code
+        elif start is not None:
+            result.append((start, code - 1))
+            start = None
+    if start is not None:
+        result.append((start, MAX - 1))
+    return result
+
+
+def is_surrogate(code):
+    return 0xD800 <= code <= 0xDFFF
+
+
+def xid_start(code):
+    c = chr(code)
+    return c != "_" and c.isidentifier()
+
+
+def xid_continue(code):
+    return ("a" + chr(code)).isidentifier()
+
+
+def canonical_decomposition(code):
+    decomposition = unicodedata.decomposition(chr(code))
+    if not decomposition or decomposition.startswith("<"):
+        return None
+    return [int(i, 16) for i in decomposition.split()]
+
+
+def char(code):
+    return "'\\u{%X}'" % code
+
+
+def emit_ranges(name, doc, values):
+    print("/// %s" % doc)
+    print("pub const %s: &[(char, char)] = &[" % name)
+    for start, end in values:
+        print("    (%s, %s)," % (char(start), char(end)))
+    print("];")
+    print()
+
+
+def main():
+    codes = [i for i in range(MAX) if not is_surrogate(i)]
+    print("// NOTE: The following code was generated by \"scripts/unicode.py\", do not edit directly")
+    print("//")
+    print("// Unicode version %s" % unicodedata.unidata_version)
+    print()
+    print("#![cfg_attr(rustfmt, rustfmt_skip)]")
+    print()
+    emit_ranges(
+        "XID_START",
+        "Ranges of characters with the `XID_Start` property",
+        ranges(lambda i: not is_surrogate(i) and xid_start(i)),
+    )
+    emit_ranges(
+        "XID_CONTINUE",
+        "Ranges of characters with the `XID_Continue` property",
+        ranges(lambda i: not is_surrogate(i) and xid_continue(i)),
+    )
+
+    print("/// Canonical combining classes of characters, except for the zero class")
+    print("pub const COMBINING_CLASSES: &[(char, char, u8)] = &[")
+    start = None
+    for code in codes + [MAX]:
+        ccc = unicodedata.combining(chr(code)) if code < MAX else 0
+        if start is not None and (ccc != start[1] or code != prev + 1):
+            print("    (%s, %s, %d)," % (char(start[0]), char(prev), start[1]))
+            start = None
+        if ccc and start is None:
+            start = (code, ccc)
+        prev = code
+    print("];")
+    print()
+
+    decompositions = []
+    compositions = []
+    for code in codes:
+        decomposition = canonical_decomposition(code)
+        if decomposition is None:
+            continue
+        decompositions.append((code, decomposition))
+        # Primary composites are the ones which are stable under NFC
+        if len(decomposition) == 2 and unicodedata.normalize("NFC", chr(code)) == chr(code):
+            compositions.append((decomposition, code))
+
+    print("/// Canonical decompositions of characters into one or two characters, except for Hangul")
+    print("///")
+    print("/// Single character decompositions have `'\\0'` as the second character.")
+    print("pub const DECOMPOSITIONS: &[(char, char, char)] = &[")
+    for code, decomposition in decompositions:
+        second = decomposition[1] if len(decomposition) == 2 else 0
+        print("    (%s, %s, %s)," % (char(code), char(decomposition[0]), char(second)))
+    print("];")
+    print()
+
+    print("/// Canonical compositions of pairs of characters, except for Hangul")
+    print("pub const COMPOSITIONS: &[(char, char, char)] = &[")
+    for (first, second), code in sorted(compositions):
+        print("    (%s, %s, %s)," % (char(first), char(second), char(code)))
+    print("];")
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,154 @@
+//! Command line arguments of the program
+
+use std::fs::{read_dir, read_to_string};
+use std::io::{self, stderr, IsTerminal};
+use std::path::Path;
+
+use rust_lexer::highlight::Theme;
+use rust_lexer::html;
+use rust_lexer::Edition;
+
+pub const USAGE: &str = "\
+Usage: rust-lexer [OPTIONS] [PATH]...
+
+Tokenize Rust sources. Directories are searched recursively for `.rs` files.
+The source is read from the standard input if no paths are given or the path is `-`.
+
+Options:
+    -o, --output FILE     Write the tokens to FILE instead of the standard output
+    -f, --format FORMAT   Output format: debug (default), spans, json, jsonl, highlight
+                          or html
+    -e, --edition YEAR    Edition of the sources: 2015 (default), 2018, 2021 or 2024
+    -l, --lossless        Output unexpected characters as Unknown tokens
+        --color WHEN      Colour the errors: auto (default), always or never
+        --theme FILE      Theme of the highlight format instead of the default one
+        --line-numbers    Number the lines of the html format and give them anchors
+        --css             Print the stylesheet of the html format
+    -h, --help            Print this message
+
+Exit status is 0 on success, 1 if the sources have lexing errors and 2 on other errors.";
+
+/// How tokens are written to the output
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Format {
+    /// `Identifier "x"`
+    Debug,
+    /// `1:5-1:6 Identifier "x"`
+    Spans,
+    /// Array of objects with the tokens and errors of each source, see `rust_lexer::json`
+    Json,
+    /// Object of a token or an error with the name of its source on each line
+    JsonLines,
+    /// The source itself with tokens coloured by the theme
+    Highlight,
+    /// The source itself with tokens in HTML spans of their classes
+    Html,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Options {
+    /// Paths of the sources and directories, `-` is the standard input
+    pub paths: Vec<String>,
+    pub output: Option<String>,
+    pub format: Format,
+    pub edition: Edition,
+    pub lossless: bool,
+    /// Whether errors are coloured with ANSI escape sequences
+    pub color: bool,
+    pub theme: Theme,
+    pub line_numbers: bool,
+}
+
+/// What the program is asked to do by its arguments
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Command {
+    /// Tokenize the sources
+    Tokenize(Options),
+    /// Print the text to the standard output and exit, e.g. the usage for `--help`
+    Print(String),
+}
+
+/// Parse the arguments of the program, without the name of the program itself
+pub fn parse_args<I>(mut args: I) -> Result<Command, String>
+where
+    I: Iterator<Item = String>,
+{
+    let mut options = Options {
+        paths: Vec::new(),
+        output: None,
+        format: Format::Debug,
+        edition: Edition::default(),
+        lossless: false,
+        color: stderr().is_terminal(),
+        theme: Theme::default(),
+        line_numbers: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value of option {}", name))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Print(format!("{}\n", USAGE))),
+            "-o" | "--output" => options.output = Some(value(&arg)?),
+            "-f" | "--format" => {
+                options.format = match value(&arg)?.as_str() {
+                    "debug" => Format::Debug,
+                    "spans" => Format::Spans,
+                    "json" => Format::Json,
+                    "jsonl" | "ndjson" => Format::JsonLines,
+                    "highlight" => Format::Highlight,
+                    "html" => Format::Html,
+                    format => return Err(format!("unknown format {}", format)),
+                }
+            }
+            "-e" | "--edition" => {
+                let year = value(&arg)?;
+                options.edition =
+                    Edition::from_year(&year).ok_or_else(|| format!("unknown edition {}", year))?;
+            }
+            "-l" | "--lossless" => options.lossless = true,
+            "--color" => {
+                options.color = match value(&arg)?.as_str() {
+                    "auto" => stderr().is_terminal(),
+                    "always" => true,
+                    "never" => false,
+                    when => return Err(format!("unknown colouring {}", when)),
+                }
+            }
+            "--theme" => {
+                let path = value(&arg)?;
+                let text = read_to_string(&path).map_err(|i| format!("{}: {}", path, i))?;
+                options.theme = Theme::parse(&text).map_err(|i| format!("{}: {}", path, i))?;
+            }
+            "--line-numbers" => options.line_numbers = true,
+            "--css" => return Ok(Command::Print(html::DEFAULT_CSS.to_string())),
+            "-" => options.paths.push(arg),
+            _ if arg.starts_with('-') => return Err(format!("unknown option {}", arg)),
+            _ => options.paths.push(arg),
+        }
+    }
+    if options.paths.is_empty() {
+        options.paths.push("-".to_string());
+    }
+    Ok(Command::Tokenize(options))
+}
+
+/// Collect `.rs` files in the directory and its subdirectories in a stable order
+pub fn find_sources(dir: &Path, sources: &mut Vec<String>) -> io::Result<()> {
+    let mut entries = read_dir(dir)?
+        .map(|i| i.map(|i| i.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        if entry.is_dir() {
+            find_sources(&entry, sources)?;
+        } else if entry.extension().and_then(|i| i.to_str()) == Some("rs") {
+            sources.push(entry.to_string_lossy().into_owned());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,102 @@
+use std::env;
+use std::fs::{create_dir_all, remove_dir_all, write};
+use std::process;
+
+use rust_lexer::highlight::Theme;
+use rust_lexer::html;
+use rust_lexer::Edition;
+
+use super::*;
+
+fn parse(args: &[&str]) -> Result<Command, String> {
+    parse_args(args.iter().map(|i| i.to_string()))
+}
+
+fn options(args: &[&str]) -> Options {
+    match parse(args) {
+        Ok(Command::Tokenize(options)) => options,
+        other => panic!("{:?} parsed into {:?}", args, other),
+    }
+}
+
+#[test]
+fn test_parse_args() {
+    let defaults = options(&[]);
+    assert_eq!(defaults.paths, ["-"]);
+    assert_eq!(defaults.output, None);
+    assert_eq!(defaults.format, Format::Debug);
+    assert_eq!(defaults.edition, Edition::default());
+    assert!(!defaults.lossless && !defaults.line_numbers);
+    assert_eq!(defaults.theme, Theme::default());
+
+    let parsed = options(&[
+        "a.rs",
+        "-f",
+        "jsonl",
+        "--output",
+        "out",
+        "-",
+        "-e",
+        "2021",
+        "-l",
+        "--color",
+        "always",
+        "src",
+        "--line-numbers",
+    ]);
+    assert_eq!(parsed.paths, ["a.rs", "-", "src"]);
+    assert_eq!(parsed.output.as_deref(), Some("out"));
+    assert_eq!(parsed.format, Format::JsonLines);
+    assert_eq!(parsed.edition, Edition::Edition2021);
+    assert!(parsed.lossless && parsed.color && parsed.line_numbers);
+    assert!(!options(&["--color", "never"]).color);
+    assert_eq!(options(&["-f", "ndjson"]).format, Format::JsonLines);
+    assert_eq!(options(&["--format", "html"]).format, Format::Html);
+
+    // Printing the usage or the stylesheet stops the parsing
+    assert_eq!(
+        parse(&["--help", "--unknown"]),
+        Ok(Command::Print(format!("{}\n", USAGE)))
+    );
+    assert_eq!(
+        parse(&["--css"]),
+        Ok(Command::Print(html::DEFAULT_CSS.to_string()))
+    );
+
+    let error = |args: &[&str]| parse(args).unwrap_err();
+    assert_eq!(error(&["-o"]), "missing value of option -o");
+    assert_eq!(error(&["--unknown"]), "unknown option --unknown");
+    assert_eq!(error(&["-f", "xml"]), "unknown format xml");
+    assert_eq!(error(&["-e", "2016"]), "unknown edition 2016");
+    assert_eq!(
+        error(&["--color", "sometimes"]),
+        "unknown colouring sometimes"
+    );
+    assert!(error(&["--theme", "/nonexistent/theme"]).starts_with("/nonexistent/theme: "));
+}
+
+#[test]
+fn test_find_sources() {
+    let dir = env::temp_dir().join(format!("rust-lexer-find-sources-{}", process::id()));
+    for path in &["b/d.rs", "b/c.rs", "a.rs", "b/e/f.rs", "notes.txt", "b/rs"] {
+        let path = dir.join(path);
+        create_dir_all(path.parent().unwrap()).unwrap();
+        write(path, "").unwrap();
+    }
+    let mut sources = Vec::new();
+    let found = find_sources(&dir, &mut sources);
+    remove_dir_all(&dir).unwrap();
+    found.unwrap();
+
+    let relative = sources
+        .iter()
+        .map(|i| Path::new(i).strip_prefix(&dir).unwrap().to_path_buf())
+        .collect::<Vec<_>>();
+    let expected = ["a.rs", "b/c.rs", "b/d.rs", "b/e/f.rs"]
+        .iter()
+        .map(|i| Path::new(i).to_path_buf())
+        .collect::<Vec<_>>();
+    assert_eq!(relative, expected);
+
+    assert!(find_sources(Path::new("/nonexistent/dir"), &mut sources).is_err());
+}
@@ -0,0 +1,390 @@
+//! Rendering of errors in the style of rustc, together with the lines of the source they point to
+//!
+//! ```text
+//! error: unknown character escape: q
+//!  --> main.rs:1:11
+//!   |
+//! 1 | let s = "a\q";
+//!   |           ^^ unknown character escape
+//!   |
+//!   = help: if you meant to write a literal backslash, escape it: `\\`
+//! ```
+//!
+//! Errors of the tokenizer are converted into diagnostics with [`From`]:
+//!
+//! ```
+//! use rust_lexer::diagnostic::Diagnostic;
+//! use rust_lexer::source_file::SourceFile;
+//!
+//! let file = SourceFile::new("main.rs", "let s = \"a\\q\";\n");
+//! for error in file.tokens().filter_map(Result::err) {
+//!     eprint!("{}", Diagnostic::from(&error).render(&file, false));
+//! }
+//! ```
+
+use std::io::{self, Write};
+
+use source_file::SourceFile;
+use token::{Position, Span};
+use tokenizer::LexError;
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+/// Colour of line numbers and the other decorations around the source
+const BLUE: &str = "\x1b[1;34m";
+
+/// Severity of a diagnostic or a note attached to it
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Level {
+    Error,
+    Warning,
+    Note,
+    Help,
+}
+
+impl Level {
+    /// Name of the level as shown before the message, e.g. `"error"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "error",
+            Level::Warning => "warning",
+            Level::Note => "note",
+            Level::Help => "help",
+        }
+    }
+
+    /// ANSI escape sequence of the colour of the level
+    fn color(self) -> &'static str {
+        match self {
+            Level::Error => "\x1b[1;31m",
+            Level::Warning => "\x1b[1;33m",
+            Level::Note => "\x1b[1;32m",
+            Level::Help => "\x1b[1;36m",
+        }
+    }
+}
+
+/// Region of the source a diagnostic points to, with an explanation
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Label {
+    pub span: Span,
+    /// Shown after the underline, can be empty
+    pub message: String,
+    /// Whether this is the location of the problem itself rather than of something related,
+    /// primary labels are underlined with `^` and secondary ones with `-`
+    pub primary: bool,
+}
+
+/// Message about a problem in the source, with the locations it concerns
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub message: String,
+    /// Locations in the source, the first primary one is shown in the header
+    pub labels: Vec<Label>,
+    /// Notes and help messages shown after the source
+    pub notes: Vec<(Level, String)>,
+}
+
+/// Span of the first two ASCII characters at the position, e.g. the `/*` of a comment
+fn two_chars(start: Position) -> Span {
+    Span {
+        start,
+        end: Position {
+            offset: start.offset + 2,
+            column: start.column + 2,
+            ..start
+        },
+    }
+}
+
+/// Width of the text on the screen, tabs are expanded to 4 spaces
+fn display_width<I: Iterator<Item = char>>(chars: I) -> usize {
+    chars.map(|i| if i == '\t' { 4 } else { 1 }).sum()
+}
+
+/// Screen column of the character at the column of the line, both starting from 1
+fn display_column(line: &str, column: usize) -> usize {
+    let before = column - 1;
+    let chars = line.chars().take(before).count();
+    display_width(line.chars().take(before)) + (before - chars) + 1
+}
+
+/// Writer of the parts of a diagnostic, which colours them if enabled
+struct Painter<'a, W: 'a + Write + ?Sized> {
+    out: &'a mut W,
+    color: bool,
+}
+
+impl<'a, W: Write + ?Sized> Painter<'a, W> {
+    fn paint(&mut self, style: &str, text: &str) -> io::Result<()> {
+        if self.color && !text.is_empty() {
+            write!(self.out, "{}{}{}", style, text, RESET)
+        } else {
+            self.out.write_all(text.as_bytes())
+        }
+    }
+
+    fn plain(&mut self, text: &str) -> io::Result<()> {
+        self.out.write_all(text.as_bytes())
+    }
+
+    /// Write the empty gutter of the given width, which starts the lines after the header
+    fn gutter(&mut self, width: usize) -> io::Result<()> {
+        self.paint(BLUE, &format!("{} |", " ".repeat(width)))
+    }
+}
+
+impl Diagnostic {
+    pub fn new<M: Into<String>>(level: Level, message: M) -> Self {
+        Diagnostic {
+            level,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn error<M: Into<String>>(message: M) -> Self {
+        Diagnostic::new(Level::Error, message)
+    }
+
+    /// Point to the location of the problem
+    pub fn with_label<M: Into<String>>(mut self, span: Span, message: M) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    /// Point to a location related to the problem
+    pub fn with_secondary_label<M: Into<String>>(mut self, span: Span, message: M) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note<M: Into<String>>(mut self, message: M) -> Self {
+        self.notes.push((Level::Note, message.into()));
+        self
+    }
+
+    pub fn with_help<M: Into<String>>(mut self, message: M) -> Self {
+        self.notes.push((Level::Help, message.into()));
+        self
+    }
+
+    /// Write the diagnostic with the lines of the file its labels point to
+    ///
+    /// Parts of the diagnostic are coloured with ANSI escape sequences if `color` is set.
+    /// Labels outside of the file, e.g. because its source is not available, are not shown, but
+    /// the header still has the location of the first primary label.
+    pub fn write<W: Write + ?Sized>(
+        &self,
+        out: &mut W,
+        file: &SourceFile,
+        color: bool,
+    ) -> io::Result<()> {
+        let shown = |span: Span| span.end.offset <= file.src().len();
+        self.write_with(out, file.name(), |i| file.line(i), shown, color)
+    }
+
+    /// Write the diagnostic with the lines of a source which is not kept as a whole, e.g. the
+    /// last lines of a source being read
+    ///
+    /// `line` gives the text of a line, numbered from 1, without the line break. Labels on lines
+    /// it does not give are not shown, like the ones outside of the file in [`Diagnostic::write`].
+    pub fn write_lines<'a, W, F>(
+        &self,
+        out: &mut W,
+        name: &str,
+        line: F,
+        color: bool,
+    ) -> io::Result<()>
+    where
+        W: Write + ?Sized,
+        F: Fn(usize) -> Option<&'a str>,
+    {
+        let shown = |span: Span| line(span.start.line).is_some() && line(span.end.line).is_some();
+        self.write_with(out, name, &line, shown, color)
+    }
+
+    /// Write the diagnostic with the labels for which `shown` is true and the lines they are on
+    fn write_with<'a, W, F, P>(
+        &self,
+        out: &mut W,
+        name: &str,
+        line_text: F,
+        shown: P,
+        color: bool,
+    ) -> io::Result<()>
+    where
+        W: Write + ?Sized,
+        F: Fn(usize) -> Option<&'a str>,
+        P: Fn(Span) -> bool,
+    {
+        let mut out = Painter { out, color };
+        out.paint(self.level.color(), self.level.as_str())?;
+        out.paint(BOLD, &format!(": {}", self.message))?;
+        out.plain("\n")?;
+
+        // Lines where the labels start or end, multiline labels are shown on these two lines
+        let mut lines = self
+            .labels
+            .iter()
+            .filter(|i| shown(i.span))
+            .flat_map(|i| vec![i.span.start.line, i.span.end.line])
+            .collect::<Vec<_>>();
+        lines.sort();
+        lines.dedup();
+        let width = lines.last().map_or(0, |i| i.to_string().len());
+
+        let main = self.labels.iter().find(|i| i.primary);
+        if let Some(label) = main.or_else(|| self.labels.first()) {
+            let start = label.span.start;
+            out.paint(BLUE, &format!("{}--> ", " ".repeat(width)))?;
+            out.plain(&format!("{}:{}:{}\n", name, start.line, start.column))?;
+        }
+        if !lines.is_empty() {
+            out.gutter(width)?;
+            out.plain("\n")?;
+        }
+        for (i, &line) in lines.iter().enumerate() {
+            if i > 0 && line > lines[i - 1] + 1 {
+                out.paint(BLUE, "...")?;
+                out.plain("\n")?;
+            }
+            let text = line_text(line).unwrap_or_default();
+            out.paint(BLUE, &format!("{:>width$} |", line, width = width))?;
+            if !text.is_empty() {
+                out.plain(&format!(" {}", text.replace('\t', "    ")))?;
+            }
+            out.plain("\n")?;
+
+            // Underlines of the labels on the line, from left to right
+            let mut underlines = Vec::new();
+            for label in &self.labels {
+                let (start, end) = (label.span.start, label.span.end);
+                if start.line != line && end.line != line || !shown(label.span) {
+                    continue;
+                }
+                let from = if start.line == line {
+                    display_column(text, start.column)
+                } else {
+                    1
+                };
+                let to = if end.line == line {
+                    display_column(text, end.column)
+                } else {
+                    display_width(text.chars()) + 1
+                };
+                let message = if end.line == line {
+                    &*label.message
+                } else {
+                    ""
+                };
+                underlines.push((from, to.max(from + 1), label.primary, message));
+            }
+            underlines.sort_by_key(|i| i.0);
+            for (from, to, primary, message) in underlines {
+                let (mark, style) = if primary {
+                    ("^", self.level.color())
+                } else {
+                    ("-", BLUE)
+                };
+                out.gutter(width)?;
+                out.plain(&" ".repeat(from))?;
+                let mut underline = mark.repeat(to - from);
+                if !message.is_empty() {
+                    underline = format!("{} {}", underline, message);
+                }
+                out.paint(style, &underline)?;
+                out.plain("\n")?;
+            }
+        }
+
+        if !self.notes.is_empty() && !lines.is_empty() {
+            out.gutter(width)?;
+            out.plain("\n")?;
+        }
+        for &(level, ref message) in &self.notes {
+            out.paint(BLUE, &format!("{} =", " ".repeat(width)))?;
+            out.plain(" ")?;
+            out.paint(BOLD, level.as_str())?;
+            out.plain(&format!(": {}\n", message))?;
+        }
+        Ok(())
+    }
+
+    /// Render the diagnostic into a string, see [`Diagnostic::write`]
+    pub fn render(&self, file: &SourceFile, color: bool) -> String {
+        let mut out = Vec::new();
+        self.write(&mut out, file, color)
+            .expect("writing into a vector failed");
+        String::from_utf8(out).expect("diagnostic is not UTF-8")
+    }
+}
+
+impl<'a> From<&'a LexError> for Diagnostic {
+    fn from(error: &'a LexError) -> Self {
+        use tokenizer::LexErrorKind::*;
+
+        let span = error.span;
+        // Unterminated literals span the rest of the source, only their start is pointed to
+        let start = Span {
+            start: span.start,
+            end: span.start,
+        };
+        let diagnostic = Diagnostic::error(error.kind.to_string());
+        match error.kind {
+            UnknownEscape(_) => diagnostic
+                .with_label(span, "unknown character escape")
+                .with_help("if you meant to write a literal backslash, escape it: `\\\\`"),
+            InvalidEscape => diagnostic.with_label(span, "invalid escape"),
+            UnescapedChar(c) => diagnostic
+                .with_label(span, "must be escaped")
+                .with_help(format!("escape the character: `{}`", c.escape_default())),
+            MultiCodepointChar => diagnostic
+                .with_label(span, "")
+                .with_help("if you meant to write a string literal, use double quotes"),
+            UnterminatedChar | UnterminatedStr => {
+                diagnostic.with_label(start, "literal starts here")
+            }
+            UnterminatedBlockComment { last_nested } => {
+                let diagnostic =
+                    diagnostic.with_label(two_chars(span.start), "unterminated block comment");
+                match last_nested {
+                    Some(nested) => diagnostic.with_secondary_label(
+                        two_chars(nested),
+                        "...as last nested comment starts here, maybe you want to close this \
+                         instead?",
+                    ),
+                    None => diagnostic,
+                }
+            }
+            InvalidDigit(_) => diagnostic.with_label(span, "invalid digit"),
+            InvalidSuffix => diagnostic.with_label(span, "invalid suffix").with_help(
+                "the suffix must be one of the numeric types (`u32`, `isize`, `f32`, etc.)",
+            ),
+            NonAsciiByte(_) => diagnostic
+                .with_label(span, "must be ASCII")
+                .with_help("use a \\xHH escape for a non-ASCII byte"),
+            UnicodeEscapeInByte => diagnostic
+                .with_label(span, "unicode escape in byte string")
+                .with_help("unicode escape sequences cannot be used as a byte or in a byte string"),
+            UnexpectedChar(_)
+            | EmptyChar
+            | NoDigits
+            | EmptyExponent
+            | NulInCStr
+            | InvalidRawStrDelimiter(_)
+            | TooManyHashes
+            | InvalidRawIdentifier(_) => diagnostic.with_label(span, ""),
+        }
+    }
+}
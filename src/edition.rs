@@ -0,0 +1,48 @@
+//! Editions of the Rust language
+
+use std::fmt;
+
+/// Edition of the Rust language, which changes the set of keywords and literals
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Debug)]
+pub enum Edition {
+    Edition2015,
+    Edition2018,
+    Edition2021,
+    Edition2024,
+}
+
+impl Edition {
+    /// Find the edition by its year, e.g. `"2018"`
+    pub fn from_year(year: &str) -> Option<Self> {
+        match year {
+            "2015" => Some(Edition::Edition2015),
+            "2018" => Some(Edition::Edition2018),
+            "2021" => Some(Edition::Edition2021),
+            "2024" => Some(Edition::Edition2024),
+            _ => None,
+        }
+    }
+
+    /// Year of the edition, e.g. `"2018"`
+    pub fn year(self) -> &'static str {
+        match self {
+            Edition::Edition2015 => "2015",
+            Edition::Edition2018 => "2018",
+            Edition::Edition2021 => "2021",
+            Edition::Edition2024 => "2024",
+        }
+    }
+}
+
+/// The edition used by rustc when none is specified
+impl Default for Edition {
+    fn default() -> Self {
+        Edition::Edition2015
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.year())
+    }
+}
@@ -0,0 +1,303 @@
+//! Syntax highlighting of sources for terminals with ANSI escape sequences
+//!
+//! Every token is coloured according to its [`Class`], the colours of the classes are defined
+//! by a [`Theme`]. Themes are written as lines of a class name and its style:
+//!
+//! ```text
+//! # Comments start with `#`
+//! keyword = bold magenta
+//! string = #a3be8c
+//! unknown = white on red
+//! ```
+//!
+//! A style is a list of attributes (`bold`, `dim`, `italic`, `underline`) and a foreground
+//! colour, optionally followed by `on` and a background colour. Colours are either one of the
+//! 8 terminal colour names (`black`, `red`, `green`, `yellow`, `blue`, `magenta`, `cyan`,
+//! `white`), possibly prefixed with `bright-`, or 24-bit colours like `#rrggbb`. Classes which
+//! are not in the theme, or have the `none` style, are not coloured.
+//! The default theme is [`DEFAULT_THEME`].
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use edition::Edition;
+use token::{Lexeme, Token};
+use tokenizer::StrTokenizer;
+
+const RESET: &str = "\x1b[0m";
+
+/// Theme used when no other is given
+pub const DEFAULT_THEME: &str = "\
+keyword = bold magenta
+identifier = none
+lifetime = cyan
+string = green
+char = green
+number = yellow
+comment = italic bright-black
+doc-comment = italic cyan
+operator = bold
+punctuation = none
+delimiter = none
+unknown = white on red
+";
+
+/// Group of tokens which are highlighted in the same way
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Class {
+    Keyword,
+    Identifier,
+    Lifetime,
+    /// String literals of all kinds
+    String,
+    /// Char and byte literals
+    Char,
+    /// Integer and float literals
+    Number,
+    /// Comments which are not doc comments and the shebang
+    Comment,
+    DocComment,
+    /// Arithmetic, logical and comparison operators, assignments and ranges
+    Operator,
+    /// Separators and the other punctuation, e.g. `::`, `;` or `#`
+    Punctuation,
+    /// Parentheses, brackets and braces
+    Delimiter,
+    /// Characters which can't start any token
+    Unknown,
+}
+
+impl Class {
+    /// All the classes in the order of their documentation
+    pub const ALL: &'static [Class] = &[
+        Class::Keyword,
+        Class::Identifier,
+        Class::Lifetime,
+        Class::String,
+        Class::Char,
+        Class::Number,
+        Class::Comment,
+        Class::DocComment,
+        Class::Operator,
+        Class::Punctuation,
+        Class::Delimiter,
+        Class::Unknown,
+    ];
+
+    /// Class of the token, `None` for whitespace
+    pub fn of(token: Token) -> Option<Class> {
+        use token::Token::*;
+
+        Some(match token {
+            Whitespace => return None,
+            Keyword(_) => Class::Keyword,
+            Identifier | Underscore => Class::Identifier,
+            IdentifierLifetime => Class::Lifetime,
+            LiteralStr { .. } => Class::String,
+            LiteralChar | LiteralByte => Class::Char,
+            LiteralInt { .. } | LiteralFloat { .. } => Class::Number,
+            Comment | Shebang => Class::Comment,
+            DocComment { .. } => Class::DocComment,
+            Equal
+            | Exclamation
+            | Question
+            | LeftArrow
+            | RightArrow
+            | FatArrow
+            | DotDot
+            | DotDotDot
+            | DotEq
+            | DotDotEq
+            | BinaryOperator(_)
+            | BinaryOperatorAssignment(_)
+            | Tilde
+            | DoubleEqual
+            | LessThan
+            | LessEqual
+            | GreaterThan
+            | GreaterEqual
+            | NotEqual
+            | DoubleAnd
+            | DoubleOr => Class::Operator,
+            Comma | Colon | DoubleColon | Semicolon | Dollar | At | Quote | Sharp | Dot => {
+                Class::Punctuation
+            }
+            Left(_) | Right(_) => Class::Delimiter,
+            Unknown => Class::Unknown,
+        })
+    }
+
+    /// Name of the class in themes, e.g. `"doc-comment"`
+    pub fn name(self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::Identifier => "identifier",
+            Class::Lifetime => "lifetime",
+            Class::String => "string",
+            Class::Char => "char",
+            Class::Number => "number",
+            Class::Comment => "comment",
+            Class::DocComment => "doc-comment",
+            Class::Operator => "operator",
+            Class::Punctuation => "punctuation",
+            Class::Delimiter => "delimiter",
+            Class::Unknown => "unknown",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Class> {
+        Class::ALL.iter().cloned().find(|i| i.name() == name)
+    }
+}
+
+/// Error in a theme, with the number of its line starting from 1
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ThemeError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at line {}", self.message, self.line)
+    }
+}
+
+impl Error for ThemeError {}
+
+/// Parameters of the SGR escape sequence of a colour, e.g. `31` or `38;2;255;0;0`
+///
+/// `base` is `30` for foreground colours and `40` for background ones.
+fn color_code(color: &str, base: u8) -> Option<String> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    if let Some(hex) = color.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+        return Some(format!(
+            "{};2;{};{};{}",
+            base + 8,
+            channel(0)?,
+            channel(2)?,
+            channel(4)?
+        ));
+    }
+    let (base, name) = match color.strip_prefix("bright-") {
+        Some(name) => (base + 60, name),
+        None => (base, color),
+    };
+    let index = NAMES.iter().position(|&i| i == name)?;
+    Some((base + index as u8).to_string())
+}
+
+/// Parameters of the SGR escape sequence of a style, empty if it is not coloured
+fn style_code(style: &str) -> Result<String, String> {
+    let mut codes = Vec::new();
+    let mut words = style.split_whitespace();
+    let mut foreground = false;
+    while let Some(word) = words.next() {
+        let code = match word {
+            "none" => continue,
+            "bold" => "1".to_string(),
+            "dim" => "2".to_string(),
+            "italic" => "3".to_string(),
+            "underline" => "4".to_string(),
+            "on" => {
+                let color = words.next().ok_or("missing background colour after `on`")?;
+                let code = color_code(color, 40)
+                    .ok_or_else(|| format!("unknown background colour `{}`", color))?;
+                codes.push(code);
+                continue;
+            }
+            _ if !foreground => {
+                foreground = true;
+                color_code(word, 30).ok_or_else(|| format!("unknown colour `{}`", word))?
+            }
+            _ => return Err(format!("unexpected `{}` after the colour", word)),
+        };
+        codes.push(code);
+    }
+    Ok(codes.join(";"))
+}
+
+/// Styles of the classes of tokens
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Theme {
+    /// Parameters of the SGR escape sequences of the coloured classes
+    styles: HashMap<Class, String>,
+}
+
+impl Theme {
+    /// Parse the text of a theme, see the [module documentation](self)
+    pub fn parse(text: &str) -> Result<Self, ThemeError> {
+        let mut styles = HashMap::new();
+        for (i, line) in text.lines().enumerate() {
+            let error = |message: String| ThemeError {
+                line: i + 1,
+                message,
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (name, style) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected `class = style`".to_string()))?;
+            let name = name.trim();
+            let class =
+                Class::from_name(name).ok_or_else(|| error(format!("unknown class `{}`", name)))?;
+            let code = style_code(style).map_err(error)?;
+            if code.is_empty() {
+                styles.remove(&class);
+            } else {
+                styles.insert(class, code);
+            }
+        }
+        Ok(Theme { styles })
+    }
+
+    /// Write the token coloured according to its class
+    ///
+    /// The colour is reset before every line break, so that the lines of multiline tokens can
+    /// be shown separately, e.g. in logs.
+    pub fn write_lexeme<W: Write + ?Sized>(&self, out: &mut W, token: &Lexeme) -> io::Result<()> {
+        let code = match Class::of(token.token).and_then(|i| self.styles.get(&i)) {
+            Some(code) => code,
+            None => return out.write_all(token.text.as_bytes()),
+        };
+        for (i, line) in token.text.split('\n').enumerate() {
+            if i > 0 {
+                out.write_all(b"\n")?;
+            }
+            if !line.is_empty() {
+                write!(out, "\x1b[{}m{}{}", code, line, RESET)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Highlight the whole source, characters which can't start a token are still shown
+    pub fn highlight(&self, src: &str, edition: Edition) -> String {
+        let mut out = Vec::new();
+        let tokens = StrTokenizer::new(src)
+            .with_edition(edition)
+            .with_lossless(true);
+        for token in tokens.filter_map(Result::ok) {
+            self.write_lexeme(&mut out, &token)
+                .expect("writing into a vector failed");
+        }
+        String::from_utf8(out).expect("highlighted source is not UTF-8")
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::parse(DEFAULT_THEME).expect("default theme is invalid")
+    }
+}
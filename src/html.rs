@@ -0,0 +1,209 @@
+//! Syntax highlighting of sources as HTML
+//!
+//! The source is written inside of `<pre class="rust"><code>`, every token which is not
+//! whitespace is a `<span>` with the name of its [`Class`] as the class, e.g.
+//! `<span class="keyword">fn</span>`. The texts are escaped, so the page shows the exact source.
+//! Lines can be numbered and have anchors, see [`HtmlWriter`]. [`DEFAULT_CSS`] is a stylesheet
+//! for all these classes.
+//!
+//! ```
+//! use rust_lexer::html;
+//! use rust_lexer::Edition;
+//!
+//! let html = html::to_html("x < 1", Edition::default(), false);
+//! assert_eq!(
+//!     html,
+//!     "<pre class=\"rust\"><code><span class=\"identifier\">x</span> \
+//!      <span class=\"operator\">&lt;</span> <span class=\"number\">1</span></code></pre>\n"
+//! );
+//! ```
+
+use std::io::{self, Write};
+
+use edition::Edition;
+use highlight::Class;
+use token::Lexeme;
+use tokenizer::StrTokenizer;
+
+/// Stylesheet for all the classes of the written HTML
+pub const DEFAULT_CSS: &str = "\
+.rust { background: #fafafa; color: #383a42; padding: 0.5em; }
+.rust .keyword { color: #a626a4; font-weight: bold; }
+.rust .lifetime { color: #0184bc; }
+.rust .string, .rust .char { color: #50a14f; }
+.rust .number { color: #986801; }
+.rust .comment { color: #a0a1a7; font-style: italic; }
+.rust .doc-comment { color: #4078f2; font-style: italic; }
+.rust .operator { font-weight: bold; }
+.rust .unknown { color: #ffffff; background: #e45649; }
+.rust .line-number {
+    display: inline-block;
+    min-width: 2em;
+    margin-right: 1em;
+    text-align: right;
+    color: #9d9d9f;
+    text-decoration: none;
+    user-select: none;
+}
+.rust .line:target { background: #fff3c4; }
+";
+
+/// Write the text with the characters special in HTML escaped
+pub fn write_str<W: Write + ?Sized>(out: &mut W, s: &str) -> io::Result<()> {
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '&' => "&amp;",
+            '<' => "&lt;",
+            '>' => "&gt;",
+            '"' => "&quot;",
+            '\'' => "&#39;",
+            _ => continue,
+        };
+        out.write_all(&s.as_bytes()[start..i])?;
+        out.write_all(escape.as_bytes())?;
+        start = i + 1;
+    }
+    out.write_all(&s.as_bytes()[start..])
+}
+
+/// Writer of the highlighted HTML of sources, which receives their tokens one by one
+///
+/// The tokens must reproduce the source, i.e. they must be read in the lossless mode. After
+/// the last token of a source [`HtmlWriter::finish`] closes its element, then the writer can be
+/// used for the next source.
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct HtmlWriter {
+    line_numbers: bool,
+    /// Prefix of the identifiers of lines
+    anchors: Option<String>,
+    /// Number of the current line
+    line: usize,
+    /// Whether the current line was started
+    in_line: bool,
+    started: bool,
+}
+
+impl HtmlWriter {
+    pub fn new() -> Self {
+        HtmlWriter::default()
+    }
+
+    /// Show the number of every line before it
+    pub fn with_line_numbers(mut self, line_numbers: bool) -> Self {
+        self.line_numbers = line_numbers;
+        self
+    }
+
+    /// Give every line an identifier made of the prefix and its number, e.g. `L12`, so that it
+    /// can be linked to
+    ///
+    /// Line numbers are then links to their lines.
+    pub fn with_anchors<P: Into<String>>(mut self, prefix: P) -> Self {
+        self.anchors = Some(prefix.into());
+        self
+    }
+
+    /// Write the opening tags, unless they were already written
+    fn start<W: Write + ?Sized>(&mut self, out: &mut W) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            self.line = 1;
+            out.write_all(b"<pre class=\"rust\"><code>")?;
+        }
+        Ok(())
+    }
+
+    /// Start the current line, unless it was already started
+    fn start_line<W: Write + ?Sized>(&mut self, out: &mut W) -> io::Result<()> {
+        self.start(out)?;
+        if self.in_line || !self.line_numbers && self.anchors.is_none() {
+            return Ok(());
+        }
+        self.in_line = true;
+        out.write_all(b"<span class=\"line\"")?;
+        if let Some(ref prefix) = self.anchors {
+            out.write_all(b" id=\"")?;
+            write_str(out, prefix)?;
+            write!(out, "{}\"", self.line)?;
+        }
+        out.write_all(b">")?;
+        match (self.line_numbers, &self.anchors) {
+            (true, Some(prefix)) => {
+                out.write_all(b"<a class=\"line-number\" href=\"#")?;
+                write_str(out, prefix)?;
+                write!(out, "{0}\">{0}</a>", self.line)
+            }
+            (true, None) => write!(out, "<span class=\"line-number\">{}</span>", self.line),
+            (false, _) => Ok(()),
+        }
+    }
+
+    /// Finish the current line and write the line break after it
+    fn end_line<W: Write + ?Sized>(&mut self, out: &mut W) -> io::Result<()> {
+        // Empty lines still have their number and anchor
+        self.start_line(out)?;
+        if self.in_line {
+            out.write_all(b"</span>")?;
+            self.in_line = false;
+        }
+        self.line += 1;
+        out.write_all(b"\n")
+    }
+
+    /// Write the token in a span of its class, multiline tokens have a span on every line
+    pub fn write_lexeme<W: Write + ?Sized>(
+        &mut self,
+        out: &mut W,
+        token: &Lexeme,
+    ) -> io::Result<()> {
+        let class = Class::of(token.token).map(Class::name);
+        for (i, text) in token.text.split('\n').enumerate() {
+            if i > 0 {
+                self.end_line(out)?;
+            }
+            if text.is_empty() {
+                continue;
+            }
+            self.start_line(out)?;
+            match class {
+                Some(class) => {
+                    write!(out, "<span class=\"{}\">", class)?;
+                    write_str(out, text)?;
+                    out.write_all(b"</span>")?;
+                }
+                None => write_str(out, text)?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the closing tags after the last token of the source
+    pub fn finish<W: Write + ?Sized>(&mut self, out: &mut W) -> io::Result<()> {
+        self.start(out)?;
+        if self.in_line {
+            out.write_all(b"</span>")?;
+            self.in_line = false;
+        }
+        self.started = false;
+        out.write_all(b"</code></pre>\n")
+    }
+}
+
+/// Highlight the whole source, optionally with numbered lines with anchors like `L12`
+pub fn to_html(src: &str, edition: Edition, line_numbers: bool) -> String {
+    let mut out = Vec::new();
+    let mut html = HtmlWriter::new();
+    if line_numbers {
+        html = html.with_line_numbers(true).with_anchors("L");
+    }
+    let tokens = StrTokenizer::new(src)
+        .with_edition(edition)
+        .with_lossless(true);
+    for token in tokens.filter_map(Result::ok) {
+        html.write_lexeme(&mut out, &token)
+            .expect("writing into a vector failed");
+    }
+    html.finish(&mut out).expect("writing into a vector failed");
+    String::from_utf8(out).expect("HTML is not UTF-8")
+}
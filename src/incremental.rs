@@ -0,0 +1,267 @@
+//! Re-lexing of sources after edits of their text
+//!
+//! An editor can keep the tokens of an open file in a [`TokenList`] and update it on every change
+//! instead of tokenizing the whole file again. Only the tokens around the edited region are lexed
+//! again, until the boundaries of the new tokens line up with the old ones after the edit, the
+//! following tokens are reused with shifted positions. An edit which opens a string or a block
+//! comment changes the meaning of the rest of the source, so the lexing goes on until the string
+//! or the comment is closed by some later text, and closing it again brings back the old tokens.
+//!
+//! The tokens are lexed in the lossless mode, so the list does not need to keep the source: its
+//! text is reproduced from the texts of the tokens.
+//!
+//! ```
+//! use rust_lexer::incremental::{TextEdit, TokenList};
+//! use rust_lexer::Edition;
+//!
+//! let mut tokens = TokenList::new("let x = 1;", Edition::default());
+//! let relexed = tokens.edit(&TextEdit { range: 4..5, text: "answer" });
+//! assert_eq!(relexed, 0..3);
+//! assert_eq!(tokens.tokens()[2].text.as_str(), "answer");
+//! assert_eq!(tokens.text(), "let answer = 1;");
+//! ```
+
+use std::ops::Range;
+
+use edition::Edition;
+use token::{Position, Span, SpannedToken, Token};
+use tokenizer::{LexError, LexErrorKind, StrTokenizer, Tokenizer};
+
+/// Replacement of a region of the source with a new text
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TextEdit<'a> {
+    /// Byte offsets of the replaced region in the source before the edit
+    pub range: Range<usize>,
+    pub text: &'a str,
+}
+
+/// Tokens and errors of a source, which can be updated after edits of its text
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TokenList {
+    tokens: Vec<SpannedToken>,
+    /// Errors in the order they were found, errors inside of a token are before the ones of the
+    /// following tokens
+    errors: Vec<LexError>,
+    edition: Edition,
+}
+
+/// Move a position at or after `from` in the old source to the same place after `to` in the
+/// new one, the text between them being the same
+fn shift(pos: Position, from: Position, to: Position) -> Position {
+    Position {
+        offset: pos.offset - from.offset + to.offset,
+        line: pos.line - from.line + to.line,
+        column: if pos.line == from.line {
+            pos.column - from.column + to.column
+        } else {
+            pos.column
+        },
+    }
+}
+
+/// Characters of the source between the byte offsets, reproduced from the texts of its tokens
+fn source_chars(
+    tokens: &[SpannedToken],
+    start: usize,
+    end: usize,
+) -> impl Iterator<Item = char> + '_ {
+    let first = tokens.partition_point(|i| i.span.end.offset <= start);
+    tokens[first..]
+        .iter()
+        .take_while(move |i| i.span.start.offset < end)
+        .flat_map(move |i| {
+            let offset = i.span.start.offset;
+            let text = i.text.as_str();
+            text[start.max(offset) - offset..(end - offset).min(text.len())].chars()
+        })
+}
+
+impl TokenList {
+    /// Lex the whole source
+    pub fn new(source: &str, edition: Edition) -> Self {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for token in StrTokenizer::new(source)
+            .with_edition(edition)
+            .with_lossless(true)
+        {
+            match token {
+                Ok(token) => tokens.push(token.to_spanned()),
+                Err(error) => errors.push(error),
+            }
+        }
+        TokenList {
+            tokens,
+            errors,
+            edition,
+        }
+    }
+
+    /// All the tokens of the source, including trivia and `Unknown` tokens
+    pub fn tokens(&self) -> &[SpannedToken] {
+        &self.tokens
+    }
+
+    pub fn errors(&self) -> &[LexError] {
+        &self.errors
+    }
+
+    /// Concatenated texts of all the tokens, which is the current source
+    pub fn text(&self) -> String {
+        self.tokens.iter().map(|i| i.text.as_str()).collect()
+    }
+
+    /// Position right after the end of the source
+    fn end(&self) -> Position {
+        self.tokens.last().map_or(Position::start(), |i| i.span.end)
+    }
+
+    /// End offset of the first token after `#!` at the start of the source which is not
+    /// whitespace or a comment, it decides whether the `#!` is a shebang
+    ///
+    /// Returns `0` if the source does not start with `#!`.
+    fn shebang_end(&self) -> usize {
+        let tokens = &self.tokens;
+        let skip = match (tokens.first(), tokens.get(1)) {
+            (Some(first), _) if first.token == Token::Shebang => 1,
+            (Some(first), Some(second))
+                if first.token == Token::Sharp
+                    && second.token == Token::Exclamation
+                    && second.span.start.offset == 1 =>
+            {
+                2
+            }
+            _ => return 0,
+        };
+        tokens[skip..]
+            .iter()
+            .find(|i| i.token != Token::Whitespace && i.token != Token::Comment)
+            .map_or(self.end().offset, |i| i.span.end.offset)
+    }
+
+    /// Update the tokens after replacing a region of the source with a new text
+    ///
+    /// Returns the range of indices of the tokens which were lexed again, the tokens after it are
+    /// the old ones with shifted positions. Errors are updated in the same way.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is not within the source or its ends are not on character boundaries.
+    pub fn edit(&mut self, edit: &TextEdit) -> Range<usize> {
+        let (start, end) = (edit.range.start, edit.range.end);
+        assert!(
+            start <= end && end <= self.end().offset,
+            "edit {:?} is out of the source",
+            edit.range
+        );
+        // Lexing of a token looks at most two characters past its end, so the first token which
+        // could see the edit is lexed again together with all the following ones
+        let tokens = &self.tokens;
+        let first = tokens.partition_point(|i| {
+            let end = i.span.end.offset;
+            let lookahead: usize = source_chars(tokens, end, usize::MAX)
+                .take(2)
+                .map(char::len_utf8)
+                .sum();
+            end + lookahead < start
+        });
+        // Whether `#!` is a shebang depends on the tokens after it, which can be far from it
+        let first = if start <= self.shebang_end() {
+            0
+        } else {
+            first
+        };
+        let (restart, after_dot) = match first.checked_sub(1) {
+            Some(i) => (self.tokens[i].span.end, self.tokens[i].token == Token::Dot),
+            None => (Position::start(), false),
+        };
+        // End of the inserted text in the new source
+        let new_end = start + edit.text.len();
+
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        // Index of the first old token which is reused and its positions in both sources
+        let resync = {
+            let chars = source_chars(&self.tokens, restart.offset, start)
+                .chain(edit.text.chars())
+                .chain(source_chars(&self.tokens, end, usize::MAX));
+            let mut lexer = Tokenizer::new(chars)
+                .with_edition(self.edition)
+                .with_lossless(true)
+                .resume_at(restart, after_dot);
+            let mut old = first;
+            // Whether the lexer is between tokens and whether the last token was `.`
+            let mut boundary = true;
+            let mut dot = after_dot;
+            loop {
+                let pos = lexer.position();
+                if boundary {
+                    // Old tokens after the edit are lexed from the same text, so the new ones
+                    // are the same from the first token starting at the same place, unless one
+                    // of them is at the start of the source, where a shebang can be
+                    while self.tokens.get(old).is_some_and(|i| {
+                        let offset = i.span.start.offset;
+                        offset < end || offset - end + new_end < pos.offset
+                    }) {
+                        old += 1;
+                    }
+                    let same = self.tokens.get(old).is_some_and(|i| {
+                        i.span.start.offset - end + new_end == pos.offset
+                            && (old > 0 && self.tokens[old - 1].token == Token::Dot) == dot
+                            && (old > 0) == (pos.offset > 0)
+                    });
+                    if same {
+                        break Some((old, self.tokens[old].span.start, pos));
+                    }
+                }
+                match lexer.next_spanned() {
+                    Some(Ok(token)) => {
+                        boundary = true;
+                        dot = token.token == Token::Dot;
+                        tokens.push(token);
+                    }
+                    Some(Err(error)) => {
+                        boundary = false;
+                        errors.push(error);
+                    }
+                    None => break None,
+                }
+            }
+        };
+
+        let (reused, from, to) = resync.unwrap_or((self.tokens.len(), self.end(), restart));
+        for token in &mut self.tokens[reused..] {
+            token.span = Span {
+                start: shift(token.span.start, from, to),
+                end: shift(token.span.end, from, to),
+            };
+        }
+        let relexed = first..first + tokens.len();
+        self.tokens.splice(first..reused, tokens);
+
+        // Errors of a token are within it, so they are split between the tokens in the same way
+        let first_error = self
+            .errors
+            .partition_point(|i| i.span.start.offset < restart.offset);
+        let reused_error = if resync.is_some() {
+            self.errors
+                .partition_point(|i| i.span.start.offset < from.offset)
+        } else {
+            self.errors.len()
+        };
+        for error in &mut self.errors[reused_error..] {
+            error.span = Span {
+                start: shift(error.span.start, from, to),
+                end: shift(error.span.end, from, to),
+            };
+            if let LexErrorKind::UnterminatedBlockComment {
+                last_nested: Some(ref mut nested),
+            } = error.kind
+            {
+                *nested = shift(*nested, from, to);
+            }
+        }
+        self.errors.splice(first_error..reused_error, errors);
+        relexed
+    }
+}
@@ -0,0 +1,285 @@
+//! JSON representation of tokens and lexing errors
+//!
+//! Every token is written as an object with the following fields:
+//!
+//! | Field     | Type           | Description                                                |
+//! |-----------|----------------|------------------------------------------------------------|
+//! | `file`    | string         | Name of the source, only present if it was given           |
+//! | `kind`    | string         | Name of the [`Token`] variant, e.g. `"BinaryOperator"`     |
+//! | `subkind` | string or null | Refinement of the kind, see below                          |
+//! | `suffix`  | string or null | Type suffix, e.g. `"u8"`, only for `LiteralInt` and `LiteralFloat` |
+//! | `raw`     | number or null | Number of `#` of a raw string, only for `LiteralStr`       |
+//! | `comment` | string         | `"Line"` or `"Block"`, only for `DocComment`               |
+//! | `spacing` | string         | [`Spacing`] of the token, `"Joint"` or `"Alone"`           |
+//! | `text`    | string         | Exact text of the token in the source                      |
+//! | `span`    | object         | Location of the token, see below                           |
+//!
+//! The `subkind` is the name of the [`PairedToken`] for `Left` and `Right`, the name of the
+//! [`BinaryOperator`] for `BinaryOperator` and `BinaryOperatorAssignment`, the keyword itself,
+//! e.g. `"fn"`, for `Keyword`, the name of the [`Base`] for `LiteralInt`, the name of the
+//! [`StrKind`] for `LiteralStr` and the name of the [`DocStyle`] for `DocComment`.
+//! It is `null` for all other tokens.
+//!
+//! The `span` has the `start` (inclusive) and `end` (exclusive) positions, both are objects with
+//! the byte `offset` from the beginning of the source, the `line` and the `column` in characters,
+//! the latter two starting from 1:
+//!
+//! ```json
+//! {"kind":"Keyword","subkind":"fn","spacing":"Alone","text":"fn","span":{"start":{"offset":0,"line":1,"column":1},"end":{"offset":2,"line":1,"column":3}}}
+//! ```
+//!
+//! Errors are objects with the `file` (if given) and `span` fields of the same meaning, the
+//! name of the [`LexErrorKind`] variant in the `error` field and the human-readable `message`:
+//!
+//! ```json
+//! {"error":"UnexpectedChar","message":"unknown start of token: `","span":{"start":{"offset":0,"line":1,"column":1},"end":{"offset":1,"line":1,"column":2}}}
+//! ```
+//!
+//! The `json` output format of the program is an array with an object for each source, which has
+//! the name of the source in the `file` field and arrays of its tokens and errors in the `tokens`
+//! and `errors` fields, without the `file` field of their own:
+//!
+//! ```json
+//! [{"file":"main.rs","tokens":[{"kind":"Identifier",...}],"errors":[{"error":"UnexpectedChar",...}]}]
+//! ```
+//!
+//! The `jsonl` format is [JSON Lines](https://jsonlines.org/): every token and error is an object
+//! on a separate line with the name of its source in the `file` field, in the order they are found
+//! in the sources:
+//!
+//! ```json
+//! {"file":"main.rs","kind":"Identifier",...}
+//! {"file":"main.rs","error":"UnexpectedChar",...}
+//! ```
+//!
+//! The names of the existing fields and values are stable, new fields may be added in the future.
+
+use std::io::{self, Write};
+
+use token::*;
+use tokenizer::{LexError, LexErrorKind};
+
+/// Name of the token variant, used as the `kind` field
+fn kind_name(token: Token) -> &'static str {
+    use token::Token::*;
+
+    match token {
+        Left(_) => "Left",
+        Right(_) => "Right",
+        Whitespace => "Whitespace",
+        Comment => "Comment",
+        DocComment { .. } => "DocComment",
+        Shebang => "Shebang",
+        Equal => "Equal",
+        Comma => "Comma",
+        Colon => "Colon",
+        DoubleColon => "DoubleColon",
+        Semicolon => "Semicolon",
+        Exclamation => "Exclamation",
+        Question => "Question",
+        Dollar => "Dollar",
+        At => "At",
+        Underscore => "Underscore",
+        Quote => "Quote",
+        Sharp => "Sharp",
+        LeftArrow => "LeftArrow",
+        RightArrow => "RightArrow",
+        FatArrow => "FatArrow",
+        Dot => "Dot",
+        DotDot => "DotDot",
+        DotDotDot => "DotDotDot",
+        DotEq => "DotEq",
+        DotDotEq => "DotDotEq",
+        BinaryOperator(_) => "BinaryOperator",
+        BinaryOperatorAssignment(_) => "BinaryOperatorAssignment",
+        Tilde => "Tilde",
+        DoubleEqual => "DoubleEqual",
+        LessThan => "LessThan",
+        LessEqual => "LessEqual",
+        GreaterThan => "GreaterThan",
+        GreaterEqual => "GreaterEqual",
+        NotEqual => "NotEqual",
+        DoubleAnd => "DoubleAnd",
+        DoubleOr => "DoubleOr",
+        LiteralInt { .. } => "LiteralInt",
+        LiteralFloat { .. } => "LiteralFloat",
+        LiteralStr { .. } => "LiteralStr",
+        LiteralChar => "LiteralChar",
+        LiteralByte => "LiteralByte",
+        Identifier => "Identifier",
+        Keyword(_) => "Keyword",
+        IdentifierLifetime => "IdentifierLifetime",
+        Unknown => "Unknown",
+    }
+}
+
+/// Name of the error variant, used as the `error` field
+fn error_name(kind: LexErrorKind) -> &'static str {
+    use tokenizer::LexErrorKind::*;
+
+    match kind {
+        UnexpectedChar(_) => "UnexpectedChar",
+        UnknownEscape(_) => "UnknownEscape",
+        InvalidEscape => "InvalidEscape",
+        UnescapedChar(_) => "UnescapedChar",
+        EmptyChar => "EmptyChar",
+        MultiCodepointChar => "MultiCodepointChar",
+        UnterminatedChar => "UnterminatedChar",
+        UnterminatedStr => "UnterminatedStr",
+        UnterminatedBlockComment { .. } => "UnterminatedBlockComment",
+        InvalidDigit(_) => "InvalidDigit",
+        NoDigits => "NoDigits",
+        EmptyExponent => "EmptyExponent",
+        InvalidSuffix => "InvalidSuffix",
+        NonAsciiByte(_) => "NonAsciiByte",
+        UnicodeEscapeInByte => "UnicodeEscapeInByte",
+        NulInCStr => "NulInCStr",
+        InvalidRawStrDelimiter(_) => "InvalidRawStrDelimiter",
+        TooManyHashes => "TooManyHashes",
+        InvalidRawIdentifier(_) => "InvalidRawIdentifier",
+    }
+}
+
+/// Write the string as a JSON string literal, with quotes
+pub fn write_str<W: Write + ?Sized>(out: &mut W, s: &str) -> io::Result<()> {
+    out.write_all(b"\"")?;
+    let mut start = 0;
+    for (i, c) in s.char_indices() {
+        let escape = match c {
+            '"' => "\\\"",
+            '\\' => "\\\\",
+            '\n' => "\\n",
+            '\r' => "\\r",
+            '\t' => "\\t",
+            '\x00'..='\x1f' | '\x7f' => "",
+            _ => continue,
+        };
+        out.write_all(&s.as_bytes()[start..i])?;
+        if escape.is_empty() {
+            write!(out, "\\u{:04x}", c as u32)?;
+        } else {
+            out.write_all(escape.as_bytes())?;
+        }
+        start = i + c.len_utf8();
+    }
+    out.write_all(&s.as_bytes()[start..])?;
+    out.write_all(b"\"")
+}
+
+/// Write the optional string as a JSON string or `null`
+fn write_opt_str<W: Write + ?Sized>(out: &mut W, s: Option<&str>) -> io::Result<()> {
+    match s {
+        Some(s) => write_str(out, s),
+        None => out.write_all(b"null"),
+    }
+}
+
+fn write_position<W: Write + ?Sized>(out: &mut W, position: Position) -> io::Result<()> {
+    write!(
+        out,
+        "{{\"offset\":{},\"line\":{},\"column\":{}}}",
+        position.offset, position.line, position.column
+    )
+}
+
+fn write_span<W: Write + ?Sized>(out: &mut W, span: Span) -> io::Result<()> {
+    out.write_all(b"{\"start\":")?;
+    write_position(out, span.start)?;
+    out.write_all(b",\"end\":")?;
+    write_position(out, span.end)?;
+    out.write_all(b"}")
+}
+
+/// Write the opening brace of an object together with the `file` field, if there is one
+fn write_file<W: Write + ?Sized>(out: &mut W, file: Option<&str>) -> io::Result<()> {
+    out.write_all(b"{")?;
+    if let Some(file) = file {
+        out.write_all(b"\"file\":")?;
+        write_str(out, file)?;
+        out.write_all(b",")?;
+    }
+    Ok(())
+}
+
+/// Write the token as a single-line JSON object, without a trailing newline
+pub fn write_token<W: Write + ?Sized>(
+    out: &mut W,
+    file: Option<&str>,
+    token: &SpannedToken,
+) -> io::Result<()> {
+    write_lexeme(
+        out,
+        file,
+        &Lexeme {
+            token: token.token,
+            span: token.span,
+            text: token.text.as_str(),
+            spacing: token.spacing,
+        },
+    )
+}
+
+/// Write the token with a borrowed text as a single-line JSON object, see [`write_token`]
+pub fn write_lexeme<W: Write + ?Sized>(
+    out: &mut W,
+    file: Option<&str>,
+    token: &Lexeme,
+) -> io::Result<()> {
+    write_file(out, file)?;
+    out.write_all(b"\"kind\":")?;
+    write_str(out, kind_name(token.token))?;
+
+    let subkind = match token.token {
+        Token::Left(paired) | Token::Right(paired) => Some(format!("{:?}", paired)),
+        Token::BinaryOperator(op) | Token::BinaryOperatorAssignment(op) => {
+            Some(format!("{:?}", op))
+        }
+        Token::Keyword(keyword) => Some(keyword.as_str().to_string()),
+        Token::LiteralInt { base, .. } => Some(format!("{:?}", base)),
+        Token::LiteralStr { kind, .. } => Some(format!("{:?}", kind)),
+        Token::DocComment { style, .. } => Some(format!("{:?}", style)),
+        _ => None,
+    };
+    out.write_all(b",\"subkind\":")?;
+    write_opt_str(out, subkind.as_deref())?;
+
+    match token.token {
+        Token::LiteralInt { suffix, .. } => {
+            out.write_all(b",\"suffix\":")?;
+            write_opt_str(out, suffix.map(IntSuffix::as_str))?;
+        }
+        Token::LiteralFloat { suffix } => {
+            out.write_all(b",\"suffix\":")?;
+            write_opt_str(out, suffix.map(FloatSuffix::as_str))?;
+        }
+        Token::LiteralStr { raw, .. } => match raw {
+            Some(hashes) => write!(out, ",\"raw\":{}", hashes)?,
+            None => out.write_all(b",\"raw\":null")?,
+        },
+        Token::DocComment { kind, .. } => write!(out, ",\"comment\":\"{:?}\"", kind)?,
+        _ => {}
+    }
+
+    write!(out, ",\"spacing\":\"{:?}\"", token.spacing)?;
+    out.write_all(b",\"text\":")?;
+    write_str(out, token.text)?;
+    out.write_all(b",\"span\":")?;
+    write_span(out, token.span)?;
+    out.write_all(b"}")
+}
+
+/// Write the error as a single-line JSON object, without a trailing newline
+pub fn write_error<W: Write + ?Sized>(
+    out: &mut W,
+    file: Option<&str>,
+    error: &LexError,
+) -> io::Result<()> {
+    write_file(out, file)?;
+    out.write_all(b"\"error\":")?;
+    write_str(out, error_name(error.kind))?;
+    out.write_all(b",\"message\":")?;
+    write_str(out, &error.kind.to_string())?;
+    out.write_all(b",\"span\":")?;
+    write_span(out, error.span)?;
+    out.write_all(b"}")
+}
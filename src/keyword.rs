@@ -0,0 +1,307 @@
+//! Keywords of the Rust language
+//!
+//! <https://doc.rust-lang.org/reference/keywords.html>
+
+use edition::Edition;
+
+/// How a keyword is treated by the language
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum KeywordKind {
+    /// Can't be used as an identifier
+    Strict,
+    /// Not used by the language yet, but can't be used as an identifier
+    Reserved,
+    /// Has a special meaning only in certain contexts, otherwise is an identifier
+    Weak,
+}
+
+/// Word which has a special meaning in some edition of the language
+///
+/// The weak keyword `'static` is a lifetime and is not listed here.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Keyword {
+    // Strict keywords
+    /// `as`
+    As,
+    /// `break`
+    Break,
+    /// `const`
+    Const,
+    /// `continue`
+    Continue,
+    /// `crate`
+    Crate,
+    /// `else`
+    Else,
+    /// `enum`
+    Enum,
+    /// `extern`
+    Extern,
+    /// `false`
+    False,
+    /// `fn`
+    Fn,
+    /// `for`
+    For,
+    /// `if`
+    If,
+    /// `impl`
+    Impl,
+    /// `in`
+    In,
+    /// `let`
+    Let,
+    /// `loop`
+    Loop,
+    /// `match`
+    Match,
+    /// `mod`
+    Mod,
+    /// `move`
+    Move,
+    /// `mut`
+    Mut,
+    /// `pub`
+    Pub,
+    /// `ref`
+    Ref,
+    /// `return`
+    Return,
+    /// `self`
+    SelfValue,
+    /// `Self`
+    SelfType,
+    /// `static`
+    Static,
+    /// `struct`
+    Struct,
+    /// `super`
+    Super,
+    /// `trait`
+    Trait,
+    /// `true`
+    True,
+    /// `type`
+    Type,
+    /// `unsafe`
+    Unsafe,
+    /// `use`
+    Use,
+    /// `where`
+    Where,
+    /// `while`
+    While,
+    // Strict keywords since 2018
+    /// `async`
+    Async,
+    /// `await`
+    Await,
+    /// `dyn`
+    Dyn,
+
+    // Reserved keywords
+    /// `abstract`
+    Abstract,
+    /// `become`
+    Become,
+    /// `box`
+    Box,
+    /// `do`
+    Do,
+    /// `final`
+    Final,
+    /// `macro`
+    Macro,
+    /// `override`
+    Override,
+    /// `priv`
+    Priv,
+    /// `typeof`
+    Typeof,
+    /// `unsized`
+    Unsized,
+    /// `virtual`
+    Virtual,
+    /// `yield`
+    Yield,
+    // Reserved keywords since 2018
+    /// `try`
+    Try,
+    // Reserved keywords since 2024
+    /// `gen`
+    Gen,
+
+    // Weak keywords
+    /// `macro_rules`
+    MacroRules,
+    /// `raw`
+    Raw,
+    /// `safe`
+    Safe,
+    /// `union`
+    Union,
+}
+
+impl Keyword {
+    /// Find the keyword with the given name
+    pub fn from_name(name: &str) -> Option<Self> {
+        use self::Keyword::*;
+
+        Some(match name {
+            "as" => As,
+            "break" => Break,
+            "const" => Const,
+            "continue" => Continue,
+            "crate" => Crate,
+            "else" => Else,
+            "enum" => Enum,
+            "extern" => Extern,
+            "false" => False,
+            "fn" => Fn,
+            "for" => For,
+            "if" => If,
+            "impl" => Impl,
+            "in" => In,
+            "let" => Let,
+            "loop" => Loop,
+            "match" => Match,
+            "mod" => Mod,
+            "move" => Move,
+            "mut" => Mut,
+            "pub" => Pub,
+            "ref" => Ref,
+            "return" => Return,
+            "self" => SelfValue,
+            "Self" => SelfType,
+            "static" => Static,
+            "struct" => Struct,
+            "super" => Super,
+            "trait" => Trait,
+            "true" => True,
+            "type" => Type,
+            "unsafe" => Unsafe,
+            "use" => Use,
+            "where" => Where,
+            "while" => While,
+            "async" => Async,
+            "await" => Await,
+            "dyn" => Dyn,
+            "abstract" => Abstract,
+            "become" => Become,
+            "box" => Box,
+            "do" => Do,
+            "final" => Final,
+            "macro" => Macro,
+            "override" => Override,
+            "priv" => Priv,
+            "typeof" => Typeof,
+            "unsized" => Unsized,
+            "virtual" => Virtual,
+            "yield" => Yield,
+            "try" => Try,
+            "gen" => Gen,
+            "macro_rules" => MacroRules,
+            "raw" => Raw,
+            "safe" => Safe,
+            "union" => Union,
+            _ => return None,
+        })
+    }
+
+    /// Name of the keyword as written in the source
+    pub fn as_str(self) -> &'static str {
+        use self::Keyword::*;
+
+        match self {
+            As => "as",
+            Break => "break",
+            Const => "const",
+            Continue => "continue",
+            Crate => "crate",
+            Else => "else",
+            Enum => "enum",
+            Extern => "extern",
+            False => "false",
+            Fn => "fn",
+            For => "for",
+            If => "if",
+            Impl => "impl",
+            In => "in",
+            Let => "let",
+            Loop => "loop",
+            Match => "match",
+            Mod => "mod",
+            Move => "move",
+            Mut => "mut",
+            Pub => "pub",
+            Ref => "ref",
+            Return => "return",
+            SelfValue => "self",
+            SelfType => "Self",
+            Static => "static",
+            Struct => "struct",
+            Super => "super",
+            Trait => "trait",
+            True => "true",
+            Type => "type",
+            Unsafe => "unsafe",
+            Use => "use",
+            Where => "where",
+            While => "while",
+            Async => "async",
+            Await => "await",
+            Dyn => "dyn",
+            Abstract => "abstract",
+            Become => "become",
+            Box => "box",
+            Do => "do",
+            Final => "final",
+            Macro => "macro",
+            Override => "override",
+            Priv => "priv",
+            Typeof => "typeof",
+            Unsized => "unsized",
+            Virtual => "virtual",
+            Yield => "yield",
+            Try => "try",
+            Gen => "gen",
+            MacroRules => "macro_rules",
+            Raw => "raw",
+            Safe => "safe",
+            Union => "union",
+        }
+    }
+
+    /// How the keyword is treated in the given edition, `None` if it is an ordinary identifier
+    pub fn kind(self, edition: Edition) -> Option<KeywordKind> {
+        use self::Keyword::*;
+
+        let since_2018 = edition >= Edition::Edition2018;
+        match self {
+            Async | Await if !since_2018 => None,
+            Dyn if !since_2018 => Some(KeywordKind::Weak),
+            Try if !since_2018 => None,
+            Gen if edition < Edition::Edition2024 => None,
+            MacroRules | Raw | Safe | Union => Some(KeywordKind::Weak),
+            Abstract | Become | Box | Do | Final | Macro | Override | Priv | Typeof | Unsized
+            | Virtual | Yield | Try | Gen => Some(KeywordKind::Reserved),
+            _ => Some(KeywordKind::Strict),
+        }
+    }
+
+    /// Whether the keyword can't be used as an identifier in the given edition
+    pub fn is_reserved(self, edition: Edition) -> bool {
+        matches!(
+            self.kind(edition),
+            Some(KeywordKind::Strict) | Some(KeywordKind::Reserved)
+        )
+    }
+
+    /// Whether the keyword could be used as a raw identifier, e.g. `r#match`
+    pub fn can_be_raw(self) -> bool {
+        !matches!(
+            self,
+            Keyword::Crate | Keyword::SelfValue | Keyword::SelfType | Keyword::Super
+        )
+    }
+}
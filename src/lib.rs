@@ -0,0 +1,56 @@
+//! Lexer of the Rust programming language
+//!
+//! The source code is turned into a stream of tokens by the [`Tokenizer`]:
+//!
+//! ```
+//! use rust_lexer::{Token, Tokenizer};
+//!
+//! let tokens = Tokenizer::new("x + 1".chars())
+//!     .spanned()
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//! assert_eq!(tokens[0].token, Token::Identifier);
+//! assert_eq!(tokens[0].text.as_str(), "x");
+//! ```
+//!
+//! Sources already in memory are scanned faster by the [`StrTokenizer`], which borrows the texts
+//! of tokens from the source:
+//!
+//! ```
+//! use rust_lexer::{StrTokenizer, Token};
+//!
+//! let source = String::from("x + 1");
+//! let lexemes = StrTokenizer::new(&source)
+//!     .collect::<Result<Vec<_>, _>>()
+//!     .unwrap();
+//! assert_eq!(lexemes[0].token, Token::Identifier);
+//! assert_eq!(lexemes[0].text, "x");
+//! ```
+
+pub mod diagnostic;
+pub mod edition;
+pub mod highlight;
+pub mod html;
+pub mod incremental;
+pub mod json;
+pub mod keyword;
+pub mod reader;
+pub mod semantic_tokens;
+pub mod source_file;
+pub mod source_map;
+pub mod symbol;
+pub mod token;
+pub mod token_tree;
+pub mod tokenizer;
+pub mod trivia;
+pub mod unicode;
+mod unicode_tables;
+
+pub use edition::Edition;
+pub use keyword::Keyword;
+pub use symbol::Symbol;
+pub use token::{Lexeme, Position, Spacing, Span, SpannedToken, Token};
+pub use tokenizer::{LexError, LexErrorKind, LexState, StrTokenizer, Tokenizer};
+
+#[cfg(test)]
+mod test;
@@ -1,21 +1,307 @@
-use std::fs::{read_to_string, write};
+extern crate rust_lexer;
 
-pub mod token;
+mod cli;
 
-use token::Tokenizer;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::env;
+use std::fs::File;
+use std::io::{self, stderr, stdin, stdout, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::exit;
+use std::rc::Rc;
 
-const INPUT: &'static str = "in.txt";
-const OUTPUT: &'static str = "out.txt";
+use rust_lexer::diagnostic::Diagnostic;
+use rust_lexer::html::HtmlWriter;
+use rust_lexer::reader::ReadChars;
+use rust_lexer::{json, LexError, Lexeme, Tokenizer};
 
-fn main() {
-    let test = read_to_string(INPUT).expect("something went wrong reading the file");
-    let tokens = Tokenizer::new(test.chars());
-    let output = tokens
-        .map(|i| format!("{:?}", i))
-        .collect::<Vec<_>>()
-        .join("\n");
-    write(OUTPUT, output).unwrap()
+use cli::{find_sources, parse_args, Command, Format, Options, USAGE};
+
+/// All sources were tokenized without errors
+const EXIT_SUCCESS: i32 = 0;
+/// Some sources have lexing errors
+const EXIT_LEX_ERROR: i32 = 1;
+/// Invalid arguments or input/output errors
+const EXIT_FAILURE: i32 = 2;
+
+fn open_source(path: &str) -> io::Result<Box<dyn BufRead>> {
+    Ok(if path == "-" {
+        Box::new(stdin().lock())
+    } else {
+        Box::new(BufReader::new(File::open(path)?))
+    })
+}
+
+fn write_token(
+    out: &mut dyn Write,
+    options: &Options,
+    html: &mut HtmlWriter,
+    name: &str,
+    token: &Lexeme,
+) -> io::Result<()> {
+    match options.format {
+        Format::Debug => writeln!(out, "{:?} {:?}", token.token, token.text),
+        Format::Spans => {
+            let (start, end) = (token.span.start, token.span.end);
+            writeln!(
+                out,
+                "{}:{}-{}:{} {:?} {:?}",
+                start.line, start.column, end.line, end.column, token.token, token.text
+            )
+        }
+        Format::Json => json::write_lexeme(out, None, token),
+        Format::JsonLines => {
+            json::write_lexeme(out, Some(name), token)?;
+            writeln!(out)
+        }
+        Format::Highlight => options.theme.write_lexeme(out, token),
+        Format::Html => html.write_lexeme(out, token),
+    }
+}
+
+/// Text of the lines of a source from a given one up to the character being read, so errors can
+/// be reported with the lines they point to without reading the source again
+struct KeptLines {
+    /// Text read from the start of line `first`
+    text: String,
+    first: usize,
+    /// Line of the next character
+    line: usize,
+}
+
+impl KeptLines {
+    fn new() -> Self {
+        KeptLines {
+            text: String::new(),
+            first: 1,
+            line: 1,
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        self.text.push(c);
+        if c == '\n' {
+            self.line += 1;
+        }
+    }
+
+    /// Forget the lines before the line, at most up to the line being read
+    fn keep_from(&mut self, line: usize) {
+        let count = line.min(self.line).saturating_sub(self.first);
+        if count > 0 {
+            // The text has a line break for each complete line
+            let end = self
+                .text
+                .match_indices('\n')
+                .nth(count - 1)
+                .map_or(0, |i| i.0 + 1);
+            self.text.drain(..end);
+            self.first += count;
+        }
+    }
+
+    /// Text of a kept line without the line break, the line being read is not complete
+    fn get(&self, line: usize) -> Option<&str> {
+        let text = self.text.split('\n').nth(line.checked_sub(self.first)?)?;
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+}
+
+/// Characters of a source which are also added to the lines shared with the reporting of errors
+struct KeepLines<I> {
+    chars: I,
+    lines: Rc<RefCell<KeptLines>>,
+}
+
+impl<I: Iterator<Item = char>> Iterator for KeepLines<I> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.lines.borrow_mut().push(c);
+        Some(c)
+    }
 }
 
-#[cfg(test)]
-mod test;
+/// Report the error to the standard error with the lines of the source it points to
+fn report_error(options: &Options, name: &str, lines: &KeptLines, error: &LexError) {
+    let stderr = stderr();
+    let mut stderr = stderr.lock();
+    // There is nothing to do if the error can't be reported
+    let _ = Diagnostic::from(error)
+        .write_lines(&mut stderr, name, |i| lines.get(i), options.color)
+        .and_then(|_| writeln!(stderr));
+}
+
+/// Tokenize the source while reading it and write its tokens, returning the exit status
+///
+/// The errors are reported to the standard error, JSON formats also include them in the output.
+fn tokenize(
+    out: &mut dyn Write,
+    options: &Options,
+    html: &mut HtmlWriter,
+    path: &str,
+    source: Box<dyn BufRead>,
+) -> io::Result<i32> {
+    let name = if path == "-" { "<stdin>" } else { path };
+    let format = options.format;
+    if format == Format::Json {
+        out.write_all(b"{\"file\":")?;
+        json::write_str(out, name)?;
+        out.write_all(b",\"tokens\":[")?;
+    }
+    // Errors are kept for the JSON format, and until the lines they point to are read
+    let mut errors: Vec<LexError> = Vec::new();
+    let mut pending: VecDeque<LexError> = VecDeque::new();
+    let mut failed = false;
+    // Highlighting shows the whole source, including unexpected characters
+    let highlight = format == Format::Highlight || format == Format::Html;
+    let lines = Rc::new(RefCell::new(KeptLines::new()));
+    let chars = KeepLines {
+        chars: ReadChars::new(source),
+        lines: lines.clone(),
+    };
+    let mut tokens = Tokenizer::new(chars)
+        .with_edition(options.edition)
+        .with_lossless(options.lossless || highlight);
+    let mut first = true;
+    // Line where the next token starts
+    let mut line = 1;
+    while let Some(token) = tokens.next_lexeme() {
+        match token {
+            Ok(token) => {
+                line = token.span.end.line;
+                if format == Format::Json && !first {
+                    out.write_all(b",")?;
+                }
+                first = false;
+                write_token(out, options, html, name, &token)?;
+            }
+            Err(error) => {
+                if format == Format::JsonLines {
+                    json::write_error(out, Some(name), &error)?;
+                    writeln!(out)?;
+                }
+                if format == Format::Json {
+                    errors.push(error);
+                }
+                pending.push_back(error);
+                failed = true;
+            }
+        }
+        // Errors are reported once the lines they point to are read
+        let mut kept = lines.borrow_mut();
+        while let Some(error) = pending.front() {
+            if error.span.end.line >= kept.line {
+                break;
+            }
+            report_error(options, name, &kept, error);
+            pending.pop_front();
+        }
+        kept.keep_from(
+            pending
+                .front()
+                .map_or(line, |i| i.span.start.line.min(line)),
+        );
+    }
+    for error in &pending {
+        report_error(options, name, &lines.borrow(), error);
+    }
+    if format == Format::Json {
+        out.write_all(b"],\"errors\":[")?;
+        for (i, error) in errors.iter().enumerate() {
+            if i > 0 {
+                out.write_all(b",")?;
+            }
+            json::write_error(out, None, error)?;
+        }
+        out.write_all(b"]}")?;
+    }
+    if format == Format::Html {
+        html.finish(out)?;
+    }
+    if let Some(error) = tokens.get_ref().chars.error() {
+        eprintln!("{}: error: {}", name, error);
+        Ok(EXIT_FAILURE)
+    } else if failed {
+        Ok(EXIT_LEX_ERROR)
+    } else {
+        Ok(EXIT_SUCCESS)
+    }
+}
+
+fn run(options: &Options) -> io::Result<i32> {
+    let mut sources = Vec::new();
+    for path in &options.paths {
+        if path != "-" && Path::new(path).is_dir() {
+            find_sources(Path::new(path), &mut sources)?;
+        } else {
+            sources.push(path.clone());
+        }
+    }
+
+    let mut out: Box<dyn Write> = match options.output {
+        Some(ref path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(BufWriter::new(stdout())),
+    };
+    let mut status = EXIT_SUCCESS;
+    let mut first = true;
+    if options.format == Format::Json {
+        out.write_all(b"[")?;
+    }
+    for path in &sources {
+        let name = if path == "-" { "<stdin>" } else { path };
+        let source = match open_source(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{}: error: {}", name, error);
+                status = EXIT_FAILURE;
+                continue;
+            }
+        };
+        match options.format {
+            Format::Debug | Format::Spans | Format::Highlight if sources.len() > 1 => {
+                writeln!(out, "==> {} <==", name)?
+            }
+            Format::Json if !first => out.write_all(b",\n")?,
+            _ => {}
+        }
+        first = false;
+        // Anchors of lines of different sources on the same page must differ
+        let mut html = HtmlWriter::new().with_line_numbers(options.line_numbers);
+        if options.line_numbers {
+            html = match sources.len() {
+                1 => html.with_anchors("L"),
+                _ => html.with_anchors(format!("{}:L", name)),
+            };
+        }
+        status = status.max(tokenize(&mut out, options, &mut html, path, source)?);
+    }
+    if options.format == Format::Json {
+        out.write_all(b"]\n")?;
+    }
+    out.flush()?;
+    Ok(status)
+}
+
+fn main() {
+    let options = match parse_args(env::args().skip(1)) {
+        Ok(Command::Tokenize(options)) => options,
+        Ok(Command::Print(text)) => {
+            print!("{}", text);
+            exit(EXIT_SUCCESS);
+        }
+        Err(error) => {
+            eprintln!("error: {}\n\n{}", error, USAGE);
+            exit(EXIT_FAILURE);
+        }
+    };
+    match run(&options) {
+        Ok(status) => exit(status),
+        Err(error) => {
+            eprintln!("error: {}", error);
+            exit(EXIT_FAILURE);
+        }
+    }
+}
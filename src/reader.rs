@@ -0,0 +1,135 @@
+//! Incremental decoding of sources read from `std::io`
+//!
+//! Large sources don't have to be read into memory before lexing. Both iterating over the
+//! tokens and [`Tokenizer::next_lexeme`](::Tokenizer::next_lexeme) use bounded memory, only
+//! [`Tokenizer::spanned`](::Tokenizer::spanned) interns the texts of all tokens, which are then
+//! kept until the end of the program:
+//!
+//! ```no_run
+//! use std::fs::File;
+//! use std::io::BufReader;
+//!
+//! use rust_lexer::Tokenizer;
+//!
+//! let file = File::open("bindings.rs").unwrap();
+//! let mut tokens = Tokenizer::from_reader(BufReader::new(file));
+//! while let Some(token) = tokens.next_lexeme() {
+//!     println!("{:?}", token);
+//! }
+//! if let Some(error) = tokens.get_ref().error() {
+//!     eprintln!("{}", error);
+//! }
+//! ```
+
+use std::io::{self, BufRead, ErrorKind};
+use std::str;
+
+/// Number of bytes in the UTF-8 sequence starting with the byte, `0` if it can't start one
+fn sequence_len(first: u8) -> usize {
+    match first {
+        0x00..=0x7f => 1,
+        0xc2..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf4 => 4,
+        _ => 0,
+    }
+}
+
+/// Iterator over the characters of a reader, which decodes UTF-8 incrementally
+///
+/// Only the buffer of the reader is kept in memory. Characters split across the boundary of
+/// the buffer are assembled from its consecutive fills.
+/// The iteration stops at the first I/O error or invalid UTF-8 sequence, which is then
+/// available from [`ReadChars::error`].
+pub struct ReadChars<R> {
+    reader: R,
+    /// Number of bytes decoded so far
+    offset: usize,
+    error: Option<io::Error>,
+}
+
+impl<R: BufRead> ReadChars<R> {
+    pub fn new(reader: R) -> Self {
+        ReadChars {
+            reader,
+            offset: 0,
+            error: None,
+        }
+    }
+
+    /// Error which stopped the iteration, if any
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Error for an invalid sequence starting at the current offset
+    fn invalid(&self) -> io::Error {
+        io::Error::new(
+            ErrorKind::InvalidData,
+            format!("invalid UTF-8 sequence at byte {}", self.offset),
+        )
+    }
+
+    fn read_char(&mut self) -> io::Result<Option<char>> {
+        let mut bytes = [0u8; 4];
+        // Number of bytes of the character collected so far and the length of its sequence
+        let mut collected = 0;
+        let mut len = 0;
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok(buf) => buf,
+                Err(ref error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            };
+            if buf.is_empty() {
+                return if collected == 0 {
+                    Ok(None)
+                } else {
+                    Err(self.invalid())
+                };
+            }
+            if collected == 0 {
+                len = sequence_len(buf[0]);
+                if len == 0 {
+                    return Err(self.invalid());
+                }
+                // The whole character is usually in the buffer
+                if len == 1 {
+                    let c = buf[0] as char;
+                    self.reader.consume(1);
+                    self.offset += 1;
+                    return Ok(Some(c));
+                }
+            }
+            let available = (len - collected).min(buf.len());
+            bytes[collected..collected + available].copy_from_slice(&buf[..available]);
+            self.reader.consume(available);
+            collected += available;
+            if collected == len {
+                let c = match str::from_utf8(&bytes[..len]) {
+                    Ok(s) => s.chars().next(),
+                    Err(_) => return Err(self.invalid()),
+                };
+                self.offset += len;
+                return Ok(c);
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ReadChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.error.is_some() {
+            return None;
+        }
+        match self.read_char() {
+            Ok(c) => c,
+            Err(error) => {
+                self.error = Some(error);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Encoding of tokens as semantic tokens of the Language Server Protocol
+//!
+//! Every token is encoded as 5 integers: the line relative to the previous token, the start
+//! relative to the previous token if it is on the same line, the length, the index of the type
+//! in the legend and the bit set of the modifiers. Lines and columns count from 0, columns and
+//! lengths are in UTF-16 code units. The legend is made of the names of [`TokenType::ALL`] and
+//! [`TokenModifier::ALL`], which are all standard types and modifiers of the protocol.
+//!
+//! ```
+//! use rust_lexer::semantic_tokens;
+//! use rust_lexer::source_file::SourceFile;
+//! use rust_lexer::Edition;
+//!
+//! let file = SourceFile::new("main.rs", "let x = 1;\n// é\n");
+//! let old = semantic_tokens::full(&file, Edition::default());
+//! assert_eq!(
+//!     old,
+//!     [0, 0, 3, 0, 0, 0, 4, 1, 1, 0, 0, 2, 1, 6, 0, 0, 2, 1, 4, 0, 1, 0, 4, 5, 0]
+//! );
+//!
+//! let file = SourceFile::new("main.rs", "let x = 10;\n// é\n");
+//! let new = semantic_tokens::full(&file, Edition::default());
+//! let edits = semantic_tokens::diff(&old, &new);
+//! assert_eq!(edits.len(), 1);
+//! assert_eq!((edits[0].start, edits[0].delete_count), (15, 5));
+//! assert_eq!(edits[0].data, [0, 2, 2, 4, 0]);
+//! ```
+
+use edition::Edition;
+use highlight::Class;
+use source_file::SourceFile;
+use token::{Span, Token};
+
+/// Number of integers encoding a token
+const TOKEN_LEN: usize = 5;
+
+/// Semantic token type of the protocol
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TokenType {
+    Keyword,
+    /// Identifiers, the lexer can't tell variables from the other items
+    Variable,
+    /// Lifetimes
+    TypeParameter,
+    /// String, char and byte literals
+    String,
+    Number,
+    /// Comments, including doc comments and the shebang
+    Comment,
+    Operator,
+}
+
+impl TokenType {
+    /// All the types in the order of the legend
+    pub const ALL: &'static [TokenType] = &[
+        TokenType::Keyword,
+        TokenType::Variable,
+        TokenType::TypeParameter,
+        TokenType::String,
+        TokenType::Number,
+        TokenType::Comment,
+        TokenType::Operator,
+    ];
+
+    /// Type of the token, `None` for tokens which are not encoded, e.g. punctuation
+    pub fn of(token: Token) -> Option<TokenType> {
+        Some(match Class::of(token)? {
+            Class::Keyword => TokenType::Keyword,
+            Class::Identifier => TokenType::Variable,
+            Class::Lifetime => TokenType::TypeParameter,
+            Class::String | Class::Char => TokenType::String,
+            Class::Number => TokenType::Number,
+            Class::Comment | Class::DocComment => TokenType::Comment,
+            Class::Operator => TokenType::Operator,
+            Class::Punctuation | Class::Delimiter | Class::Unknown => return None,
+        })
+    }
+
+    /// Name of the type in the protocol, e.g. `"typeParameter"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TokenType::Keyword => "keyword",
+            TokenType::Variable => "variable",
+            TokenType::TypeParameter => "typeParameter",
+            TokenType::String => "string",
+            TokenType::Number => "number",
+            TokenType::Comment => "comment",
+            TokenType::Operator => "operator",
+        }
+    }
+
+    /// Index of the type in the legend
+    pub fn index(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Semantic token modifier of the protocol
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum TokenModifier {
+    /// Doc comments
+    Documentation,
+}
+
+impl TokenModifier {
+    /// All the modifiers in the order of the legend
+    pub const ALL: &'static [TokenModifier] = &[TokenModifier::Documentation];
+
+    /// Modifiers of the token
+    pub fn of(token: Token) -> Vec<TokenModifier> {
+        match token {
+            Token::DocComment { .. } => vec![TokenModifier::Documentation],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Name of the modifier in the protocol, e.g. `"documentation"`
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TokenModifier::Documentation => "documentation",
+        }
+    }
+
+    /// Bit of the modifier in the bit sets of modifiers
+    pub fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Encode the tokens of the file, which must be in the order of the source
+///
+/// Multiline tokens are split into a token on every line, because not all clients support
+/// multiline tokens.
+pub fn encode<I>(file: &SourceFile, tokens: I) -> Vec<u32>
+where
+    I: IntoIterator<Item = (Token, Span)>,
+{
+    let mut data = Vec::new();
+    let (mut prev_line, mut prev_start) = (0, 0);
+    for (token, span) in tokens {
+        let token_type = match TokenType::of(token) {
+            Some(token_type) => token_type.index(),
+            None => continue,
+        };
+        let modifiers = TokenModifier::of(token)
+            .into_iter()
+            .fold(0, |set, i| set | i.bit());
+        for line in span.start.line..=span.end.line {
+            let (line_start, text) = match (file.line_start(line), file.line(line)) {
+                (Some(start), Some(text)) => (start, text),
+                _ => break,
+            };
+            let from = span.start.offset.max(line_start);
+            let to = span.end.offset.min(line_start + text.len());
+            if from >= to {
+                continue;
+            }
+            let length = file.src()[from..to]
+                .chars()
+                .map(char::len_utf16)
+                .sum::<usize>();
+            let (line, start) = (line as u32 - 1, file.utf16_column(from) as u32 - 1);
+            let delta_start = if line == prev_line {
+                start - prev_start
+            } else {
+                start
+            };
+            data.extend_from_slice(&[
+                line - prev_line,
+                delta_start,
+                length as u32,
+                token_type,
+                modifiers,
+            ]);
+            prev_line = line;
+            prev_start = start;
+        }
+    }
+    data
+}
+
+/// Encode all the tokens of the file, as the response to `textDocument/semanticTokens/full`
+pub fn full(file: &SourceFile, edition: Edition) -> Vec<u32> {
+    let tokens = file
+        .tokens()
+        .with_edition(edition)
+        .filter_map(Result::ok)
+        .map(|i| (i.token, i.span));
+    encode(file, tokens)
+}
+
+/// Replacement of integers of the previous encoded tokens
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SemanticTokensEdit {
+    /// Index of the first replaced integer
+    pub start: u32,
+    /// Number of replaced integers
+    pub delete_count: u32,
+    pub data: Vec<u32>,
+}
+
+/// Edits turning the previous encoded tokens into the new ones, as the response to
+/// `textDocument/semanticTokens/full/delta`
+///
+/// The edit replaces whole tokens, between the tokens common to the starts and the ends of both
+/// encodings. There are no edits if the encodings are the same.
+pub fn diff(old: &[u32], new: &[u32]) -> Vec<SemanticTokensEdit> {
+    let old_tokens = old.chunks(TOKEN_LEN);
+    let new_tokens = new.chunks(TOKEN_LEN);
+    let prefix = old_tokens
+        .clone()
+        .zip(new_tokens.clone())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let max_suffix = old_tokens.len().min(new_tokens.len()) - prefix;
+    let suffix = old_tokens
+        .rev()
+        .zip(new_tokens.rev())
+        .take(max_suffix)
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (start, old_end, new_end) = (
+        prefix * TOKEN_LEN,
+        old.len() - suffix * TOKEN_LEN,
+        new.len() - suffix * TOKEN_LEN,
+    );
+    if start == old_end && start == new_end {
+        return Vec::new();
+    }
+    vec![SemanticTokensEdit {
+        start: start as u32,
+        delete_count: (old_end - start) as u32,
+        data: new[start..new_end].to_vec(),
+    }]
+}
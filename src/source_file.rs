@@ -0,0 +1,120 @@
+//! Sources in memory with an index of their lines
+
+use token::{Position, Span};
+use tokenizer::StrTokenizer;
+
+/// Source code with its name, e.g. the path of the file, and the offsets of its lines
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SourceFile {
+    name: String,
+    src: String,
+    /// Byte offsets of the starts of lines, the first one is always `0`
+    lines: Vec<usize>,
+}
+
+impl SourceFile {
+    pub fn new<N, S>(name: N, src: S) -> Self
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        let src = src.into();
+        let lines = Some(0)
+            .into_iter()
+            .chain(src.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        SourceFile {
+            name: name.into(),
+            src,
+            lines,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn src(&self) -> &str {
+        &self.src
+    }
+
+    /// Tokenizer scanning the source
+    pub fn tokens(&self) -> StrTokenizer<'_> {
+        StrTokenizer::new(&self.src)
+    }
+
+    /// Number of lines, a line break at the end starts an empty last line
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Byte offset of the start of the line, numbered from 1
+    pub fn line_start(&self, line: usize) -> Option<usize> {
+        self.lines.get(line.checked_sub(1)?).cloned()
+    }
+
+    /// Text of the line, numbered from 1, without the line break
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = self.line_start(line)?;
+        let end = self.line_start(line + 1).unwrap_or(self.src.len());
+        let text = &self.src[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// Number of the line containing the byte offset, starting from 1
+    pub fn lookup_line(&self, offset: usize) -> usize {
+        self.lines.partition_point(|&i| i <= offset)
+    }
+
+    /// Position of the byte offset, which must be a char boundary not past the end of the source
+    pub fn position(&self, offset: usize) -> Position {
+        let line = self.lookup_line(offset);
+        let start = self.lines[line - 1];
+        Position {
+            offset,
+            line,
+            column: self.src[start..offset].chars().count() + 1,
+        }
+    }
+
+    /// Column of the byte offset in bytes, starting from 1
+    pub fn byte_column(&self, offset: usize) -> usize {
+        offset - self.lines[self.lookup_line(offset) - 1] + 1
+    }
+
+    /// Column of the byte offset in UTF-16 code units, starting from 1
+    ///
+    /// This is the column used by the Language Server Protocol, where it starts from 0.
+    pub fn utf16_column(&self, offset: usize) -> usize {
+        let start = self.lines[self.lookup_line(offset) - 1];
+        let before = &self.src[start..offset];
+        if before.is_ascii() {
+            return before.len() + 1;
+        }
+        before.chars().map(char::len_utf16).sum::<usize>() + 1
+    }
+
+    /// Byte offset of the column in UTF-16 code units on the line, both starting from 1
+    ///
+    /// Returns `None` if the column is past the end of the line or in the middle of a character.
+    pub fn utf16_offset(&self, line: usize, column: usize) -> Option<usize> {
+        let start = self.line_start(line)?;
+        let mut units = column.checked_sub(1)?;
+        for (i, c) in self.line(line)?.char_indices() {
+            if units == 0 {
+                return Some(start + i);
+            }
+            units = units.checked_sub(c.len_utf16())?;
+        }
+        match units {
+            0 => Some(start + self.line(line)?.len()),
+            _ => None,
+        }
+    }
+
+    /// Text of the source in the span
+    pub fn text(&self, span: Span) -> &str {
+        &self.src[span.start.offset..span.end.offset]
+    }
+}
@@ -0,0 +1,120 @@
+//! Sets of source files with positions global to all of them
+//!
+//! Positions of tokens are relative to their file, so tokens of different files can't be told
+//! apart by them. A [`SourceMap`] gives every file a separate range of [`GlobalPos`] values, in
+//! the order the files were added, which identify both the file and the place in it:
+//!
+//! ```
+//! use rust_lexer::source_map::SourceMap;
+//!
+//! let mut map = SourceMap::new();
+//! let lib = map.add_file("lib.rs", "mod a;");
+//! let a = map.add_file("a.rs", "fn f() {}");
+//! let token = map.file(a).tokens().next().unwrap().unwrap();
+//! let span = map.global_span(a, token.span);
+//! assert!(span.start > map.global_pos(lib, 0));
+//! assert_eq!(map.lookup(span.start), Some((a, token.span.start)));
+//! ```
+
+use source_file::SourceFile;
+use token::{Position, Span};
+
+/// Position in the space of all files of a source map
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Debug, Default)]
+pub struct GlobalPos(pub usize);
+
+/// Region of one of the files of a source map in global positions
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Debug)]
+pub struct GlobalSpan {
+    pub start: GlobalPos,
+    pub end: GlobalPos,
+}
+
+/// Index of a file in its source map
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Debug)]
+pub struct FileId(usize);
+
+/// Files together with their ranges of global positions
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    /// Global position of the start of each file
+    starts: Vec<usize>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap::default()
+    }
+
+    /// Add a file, its global positions are after the ones of all the files added before
+    pub fn add_file<N, S>(&mut self, name: N, src: S) -> FileId
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        // The position at the end of a file is still in its range
+        let start = match (self.files.last(), self.starts.last()) {
+            (Some(file), Some(&start)) => start + file.src().len() + 1,
+            _ => 0,
+        };
+        self.files.push(SourceFile::new(name, src));
+        self.starts.push(start);
+        FileId(self.files.len() - 1)
+    }
+
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    /// All the files in the order they were added
+    pub fn files(&self) -> impl Iterator<Item = (FileId, &SourceFile)> {
+        self.files
+            .iter()
+            .enumerate()
+            .map(|(i, file)| (FileId(i), file))
+    }
+
+    /// Global position of the byte offset in the file
+    pub fn global_pos(&self, id: FileId, offset: usize) -> GlobalPos {
+        GlobalPos(self.starts[id.0] + offset)
+    }
+
+    /// Global span of the span in the file, e.g. of a token read from it
+    pub fn global_span(&self, id: FileId, span: Span) -> GlobalSpan {
+        GlobalSpan {
+            start: self.global_pos(id, span.start.offset),
+            end: self.global_pos(id, span.end.offset),
+        }
+    }
+
+    /// File containing the global position, if any
+    pub fn lookup_file(&self, pos: GlobalPos) -> Option<FileId> {
+        let id = self
+            .starts
+            .partition_point(|&i| i <= pos.0)
+            .checked_sub(1)?;
+        if pos.0 - self.starts[id] <= self.files[id].src().len() {
+            Some(FileId(id))
+        } else {
+            None
+        }
+    }
+
+    /// File containing the global position and the position in it
+    pub fn lookup(&self, pos: GlobalPos) -> Option<(FileId, Position)> {
+        let id = self.lookup_file(pos)?;
+        Some((id, self.file(id).position(pos.0 - self.starts[id.0])))
+    }
+
+    /// File containing the global span and the span in it
+    pub fn lookup_span(&self, span: GlobalSpan) -> Option<(FileId, Span)> {
+        let (id, start) = self.lookup(span.start)?;
+        let (end_id, end) = self.lookup(span.end)?;
+        if id == end_id {
+            Some((id, Span { start, end }))
+        } else {
+            None
+        }
+    }
+}
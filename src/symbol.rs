@@ -0,0 +1,71 @@
+//! Interning of token texts
+//!
+//! Every distinct string is stored only once and is referred to by a small copyable `Symbol`,
+//! so tokens can carry their text around cheaply and be compared by it in constant time.
+//! Interned strings live until the end of the program.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+
+/// Interned string, e.g. the text of a token
+#[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Find the symbol of the given string, interning it if it is new
+    pub fn intern(string: &str) -> Self {
+        with_interner(|interner| interner.intern(string))
+    }
+
+    /// Get the string this symbol refers to
+    pub fn as_str(self) -> &'static str {
+        with_interner(|interner| interner.strings[self.0 as usize])
+    }
+
+    /// Index of the symbol, unique among all interned strings
+    pub fn as_u32(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Storage of all interned strings
+#[derive(Default)]
+struct Interner {
+    names: HashMap<&'static str, Symbol>,
+    strings: Vec<&'static str>,
+}
+
+impl Interner {
+    fn intern(&mut self, string: &str) -> Symbol {
+        if let Some(&symbol) = self.names.get(string) {
+            return symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        let string: &'static str = Box::leak(string.into());
+        self.strings.push(string);
+        self.names.insert(string, symbol);
+        symbol
+    }
+}
+
+fn with_interner<T, F: FnOnce(&mut Interner) -> T>(f: F) -> T {
+    static INTERNER: OnceLock<Mutex<Interner>> = OnceLock::new();
+    let mut interner = INTERNER
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    f(&mut interner)
+}
@@ -1,29 +1,67 @@
+use diagnostic::{Diagnostic, Level};
+use edition::Edition;
+use highlight::{self, Class, Theme};
+use incremental::{TextEdit, TokenList};
+use json;
+use keyword::Keyword as Kw;
+use reader::ReadChars;
+use source_file::SourceFile;
+use source_map::{GlobalSpan, SourceMap};
+use symbol::Symbol;
 use token::BinaryOperator::*;
 use token::PairedToken::*;
 use token::Token::*;
 use token::*;
+use token_tree;
+use tokenizer::*;
+use trivia;
 
 struct TestCase(&'static str, &'static [Token]);
 
+const INT: Token = LiteralInt {
+    base: Base::Decimal,
+    suffix: None,
+};
+const FLOAT: Token = LiteralFloat { suffix: None };
+const STR: Token = LiteralStr {
+    kind: StrKind::Str,
+    raw: None,
+};
+
+struct ErrorTestCase(&'static str, &'static [Result<Token, LexErrorKind>]);
+
 const TESTS: &[TestCase] = &[
-    TestCase("255+1488", &[LiteralInt, BinaryOperator(Plus), LiteralInt]),
+    TestCase("255+1488", &[INT, BinaryOperator(Plus), INT]),
     TestCase(
         "<>=<<=1 != ==5",
         &[
             LessThan,
             GreaterEqual,
             BinaryOperatorAssignment(Shl),
-            LiteralInt,
+            INT,
             Whitespace,
             NotEqual,
             Whitespace,
             DoubleEqual,
-            LiteralInt,
+            INT,
         ],
     ),
     TestCase(
         "\"This is a bucket!\"+\"Dear\\ngod\"",
-        &[LiteralStr, BinaryOperator(Plus), LiteralStr],
+        &[STR, BinaryOperator(Plus), STR],
+    ),
+    TestCase(
+        "\"a\\\n  b\" \"c\\\r\n\td\" b\"e\\\r\n\"",
+        &[
+            STR,
+            Whitespace,
+            STR,
+            Whitespace,
+            LiteralStr {
+                kind: StrKind::Byte,
+                raw: None,
+            },
+        ],
     ),
     TestCase(
         "- = -====",
@@ -40,24 +78,24 @@ const TESTS: &[TestCase] = &[
     TestCase(
         "2+2//сложение чисел\n3+=3",
         &[
-            LiteralInt,
+            INT,
             BinaryOperator(Plus),
-            LiteralInt,
+            INT,
             Comment,
             Whitespace,
-            LiteralInt,
+            INT,
             BinaryOperatorAssignment(Plus),
-            LiteralInt,
+            INT,
         ],
     ),
     TestCase(
         "2+/* block comment */3",
-        &[LiteralInt, BinaryOperator(Plus), Comment, LiteralInt],
+        &[INT, BinaryOperator(Plus), Comment, INT],
     ),
     TestCase(
         "struct TestCase(&'static str, &'static [Token]);",
         &[
-            Identifier,
+            Keyword(Kw::Struct),
             Whitespace,
             Identifier,
             Left(Parenthesis),
@@ -80,7 +118,7 @@ const TESTS: &[TestCase] = &[
     TestCase(
         "fn kek<'cool_lifetyme_1337, T>(shrek: & 'cool_lifetyme_1337 mut T) -> T {}",
         &[
-            Identifier,
+            Keyword(Kw::Fn),
             Whitespace,
             Identifier,
             LessThan,
@@ -97,7 +135,7 @@ const TESTS: &[TestCase] = &[
             Whitespace,
             IdentifierLifetime,
             Whitespace,
-            Identifier,
+            Keyword(Kw::Mut),
             Whitespace,
             Identifier,
             Right(Parenthesis),
@@ -113,16 +151,25 @@ const TESTS: &[TestCase] = &[
     TestCase(
         "  let mut a = 3;",
         &[
-            Whitespace, Identifier, Whitespace, Identifier, Whitespace, Identifier, Whitespace,
-            Equal, Whitespace, LiteralInt, Semicolon,
+            Whitespace,
+            Keyword(Kw::Let),
+            Whitespace,
+            Keyword(Kw::Mut),
+            Whitespace,
+            Identifier,
+            Whitespace,
+            Equal,
+            Whitespace,
+            INT,
+            Semicolon,
         ],
     ),
     TestCase(
         "let mut vec = Vec::new(   );",
         &[
-            Identifier,
+            Keyword(Kw::Let),
             Whitespace,
-            Identifier,
+            Keyword(Kw::Mut),
             Whitespace,
             Identifier,
             Whitespace,
@@ -143,7 +190,7 @@ const TESTS: &[TestCase] = &[
                 println!(\"{}\", top);\n
                 }",
         &[
-            Identifier,
+            Keyword(Kw::Let),
             Whitespace,
             Identifier,
             Whitespace,
@@ -152,16 +199,16 @@ const TESTS: &[TestCase] = &[
             Identifier,
             Exclamation,
             Left(Bracket),
-            LiteralInt,
+            INT,
             Semicolon,
             Whitespace,
-            LiteralInt,
+            INT,
             Right(Bracket),
             Semicolon,
             Whitespace,
-            Identifier,
+            Keyword(Kw::While),
             Whitespace,
-            Identifier,
+            Keyword(Kw::Let),
             Whitespace,
             Identifier,
             Left(Parenthesis),
@@ -183,7 +230,7 @@ const TESTS: &[TestCase] = &[
             Identifier,
             Exclamation,
             Left(Parenthesis),
-            LiteralStr,
+            STR,
             Comma,
             Whitespace,
             Identifier,
@@ -196,14 +243,14 @@ const TESTS: &[TestCase] = &[
     TestCase(
         "2-+6*7^311231;\n",
         &[
-            LiteralInt,
+            INT,
             BinaryOperator(Minus),
             BinaryOperator(Plus),
-            LiteralInt,
+            INT,
             BinaryOperator(Star),
-            LiteralInt,
+            INT,
             BinaryOperator(Caret),
-            LiteralInt,
+            INT,
             Semicolon,
             Whitespace,
         ],
@@ -215,40 +262,1700 @@ const TESTS: &[TestCase] = &[
             Identifier,
             BinaryOperatorAssignment(Shl),
             Left(Parenthesis),
-            LiteralInt,
+            INT,
             BinaryOperator(Or),
-            LiteralInt,
+            INT,
             Right(Parenthesis),
             Semicolon,
             Whitespace,
             Identifier,
             BinaryOperatorAssignment(Shr),
             Left(Parenthesis),
-            LiteralInt,
+            INT,
             BinaryOperator(And),
             Left(Parenthesis),
-            LiteralInt,
+            INT,
             BinaryOperator(Percent),
-            LiteralInt,
+            INT,
+            Right(Parenthesis),
             Right(Parenthesis),
+        ],
+    ),
+    TestCase(
+        "1.5 1e10 1.0E-3_f64 2. 2f32 0xFF 0o17 0b1010_1010 1_000u64 0x1f32 0xffu8",
+        &[
+            FLOAT,
+            Whitespace,
+            FLOAT,
+            Whitespace,
+            LiteralFloat {
+                suffix: Some(FloatSuffix::F64),
+            },
+            Whitespace,
+            FLOAT,
+            Whitespace,
+            LiteralFloat {
+                suffix: Some(FloatSuffix::F32),
+            },
+            Whitespace,
+            LiteralInt {
+                base: Base::Hexadecimal,
+                suffix: None,
+            },
+            Whitespace,
+            LiteralInt {
+                base: Base::Octal,
+                suffix: None,
+            },
+            Whitespace,
+            LiteralInt {
+                base: Base::Binary,
+                suffix: None,
+            },
+            Whitespace,
+            LiteralInt {
+                base: Base::Decimal,
+                suffix: Some(IntSuffix::U64),
+            },
+            Whitespace,
+            LiteralInt {
+                base: Base::Hexadecimal,
+                suffix: None,
+            },
+            Whitespace,
+            LiteralInt {
+                base: Base::Hexadecimal,
+                suffix: Some(IntSuffix::U8),
+            },
+        ],
+    ),
+    TestCase(
+        "1..2 1.max(2) x.0.1 0.5..=1.",
+        &[
+            INT,
+            DotDot,
+            INT,
+            Whitespace,
+            INT,
+            Dot,
+            Identifier,
+            Left(Parenthesis),
+            INT,
             Right(Parenthesis),
+            Whitespace,
+            Identifier,
+            Dot,
+            INT,
+            Dot,
+            INT,
+            Whitespace,
+            FLOAT,
+            DotDotEq,
+            FLOAT,
+        ],
+    ),
+    TestCase(
+        "r\"a\\b\" r#\"x\"y\"# br##\"\"#\"## b\"\\xff\" c\"\\u{1F600}\" cr\"\" b'\\'' r#x bread",
+        &[
+            LiteralStr {
+                kind: StrKind::Str,
+                raw: Some(0),
+            },
+            Whitespace,
+            LiteralStr {
+                kind: StrKind::Str,
+                raw: Some(1),
+            },
+            Whitespace,
+            LiteralStr {
+                kind: StrKind::Byte,
+                raw: Some(2),
+            },
+            Whitespace,
+            LiteralStr {
+                kind: StrKind::Byte,
+                raw: None,
+            },
+            Whitespace,
+            // C strings are not supported in 2015
+            Identifier,
+            STR,
+            Whitespace,
+            Identifier,
+            STR,
+            Whitespace,
+            LiteralByte,
+            Whitespace,
+            Identifier,
+            Whitespace,
+            Identifier,
+        ],
+    ),
+];
+
+const ERROR_TESTS: &[ErrorTestCase] = &[
+    ErrorTestCase(
+        "b'ы' b\"\\u{0}\" c\"\\0\" r##- r#\"a\"",
+        &[
+            Err(LexErrorKind::NonAsciiByte('ы')),
+            Ok(LiteralByte),
+            Ok(Whitespace),
+            Err(LexErrorKind::UnicodeEscapeInByte),
+            Ok(LiteralStr {
+                kind: StrKind::Byte,
+                raw: None,
+            }),
+            Ok(Whitespace),
+            Ok(Identifier),
+            Ok(STR),
+            Ok(Whitespace),
+            Err(LexErrorKind::InvalidRawStrDelimiter('-')),
+            Ok(LiteralStr {
+                kind: StrKind::Str,
+                raw: Some(2),
+            }),
+            Ok(BinaryOperator(Minus)),
+            Ok(Whitespace),
+            Err(LexErrorKind::UnterminatedStr),
+            Ok(LiteralStr {
+                kind: StrKind::Str,
+                raw: Some(1),
+            }),
+        ],
+    ),
+    ErrorTestCase(
+        "0b102 0x 1e+ 1u7 1.5u8",
+        &[
+            Err(LexErrorKind::InvalidDigit('2')),
+            Ok(LiteralInt {
+                base: Base::Binary,
+                suffix: None,
+            }),
+            Ok(Whitespace),
+            Err(LexErrorKind::NoDigits),
+            Ok(LiteralInt {
+                base: Base::Hexadecimal,
+                suffix: None,
+            }),
+            Ok(Whitespace),
+            Err(LexErrorKind::EmptyExponent),
+            Ok(FLOAT),
+            Ok(Whitespace),
+            Err(LexErrorKind::InvalidSuffix),
+            Ok(INT),
+            Ok(Whitespace),
+            Err(LexErrorKind::InvalidSuffix),
+            Ok(FLOAT),
+        ],
+    ),
+    ErrorTestCase(
+        "r#_ r#crate r#Self r#super r#x_",
+        &[
+            Err(LexErrorKind::InvalidRawIdentifier("_")),
+            Ok(Identifier),
+            Ok(Whitespace),
+            Err(LexErrorKind::InvalidRawIdentifier("crate")),
+            Ok(Identifier),
+            Ok(Whitespace),
+            Err(LexErrorKind::InvalidRawIdentifier("Self")),
+            Ok(Identifier),
+            Ok(Whitespace),
+            Err(LexErrorKind::InvalidRawIdentifier("super")),
+            Ok(Identifier),
+            Ok(Whitespace),
+            Ok(Identifier),
+        ],
+    ),
+    ErrorTestCase(
+        "0e 1E 2ex",
+        &[
+            Err(LexErrorKind::EmptyExponent),
+            Ok(FLOAT),
+            Ok(Whitespace),
+            Err(LexErrorKind::EmptyExponent),
+            Ok(FLOAT),
+            Ok(Whitespace),
+            Err(LexErrorKind::EmptyExponent),
+            Err(LexErrorKind::InvalidSuffix),
+            Ok(FLOAT),
+        ],
+    ),
+    ErrorTestCase(
+        "a ` b",
+        &[
+            Ok(Identifier),
+            Ok(Whitespace),
+            Err(LexErrorKind::UnexpectedChar('`')),
+            Ok(Whitespace),
+            Ok(Identifier),
+        ],
+    ),
+    ErrorTestCase(
+        "\"\\q\\x80\" 1",
+        &[
+            Err(LexErrorKind::UnknownEscape('q')),
+            Err(LexErrorKind::InvalidEscape),
+            Ok(STR),
+            Ok(Whitespace),
+            Ok(INT),
+        ],
+    ),
+    ErrorTestCase(
+        "'ab' '' '12'",
+        &[
+            Err(LexErrorKind::MultiCodepointChar),
+            Ok(LiteralChar),
+            Ok(Whitespace),
+            Err(LexErrorKind::EmptyChar),
+            Ok(LiteralChar),
+            Ok(Whitespace),
+            Err(LexErrorKind::MultiCodepointChar),
+            Ok(LiteralChar),
+        ],
+    ),
+    ErrorTestCase(
+        "'\\u{110000}' '\t'",
+        &[
+            Err(LexErrorKind::InvalidEscape),
+            Ok(LiteralChar),
+            Ok(Whitespace),
+            Err(LexErrorKind::UnescapedChar('\t')),
+            Ok(LiteralChar),
+        ],
+    ),
+    ErrorTestCase(
+        "'1\nx",
+        &[
+            Err(LexErrorKind::UnterminatedChar),
+            Ok(LiteralChar),
+            Ok(Whitespace),
+            Ok(Identifier),
+        ],
+    ),
+    ErrorTestCase("\"abc", &[Err(LexErrorKind::UnterminatedStr), Ok(STR)]),
+    ErrorTestCase(
+        "/* a **/1/* b",
+        &[
+            Ok(Comment),
+            Ok(INT),
+            Err(LexErrorKind::UnterminatedBlockComment { last_nested: None }),
+            Ok(Comment),
         ],
     ),
 ];
 
 fn tokenize(input: &str) -> Vec<Token> {
-    Tokenizer::new(input.chars()).collect()
+    Tokenizer::new(input.chars())
+        .collect::<Result<_, _>>()
+        .unwrap()
 }
 
 #[test]
 fn test_custom_positive() {
     for TestCase(input, output) in TESTS {
-        let tokens = tokenize(&input);
+        let tokens = tokenize(input);
         assert_eq!(tokens, *output);
         println!("Expression {} tokenized successfully!", input);
     }
 }
 
+#[test]
+fn test_custom_negative() {
+    for ErrorTestCase(input, output) in ERROR_TESTS {
+        let tokens = Tokenizer::new(input.chars())
+            .map(|i| i.map_err(|e| e.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(tokens, *output);
+        // Scanning the string directly gives the same result
+        let lexemes = StrTokenizer::new(input)
+            .map(|i| i.map(|i| i.token).map_err(|e| e.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(lexemes, *output);
+        println!("Errors in expression {} reported successfully!", input);
+    }
+}
+
+#[test]
+fn test_punctuation() {
+    // Every punctuation token listed in the Rust reference
+    let input = "+ - * / % ^ ! & | && || << >> += -= *= /= %= ^= &= |= <<= >>= = == != > < >= <= \
+                 @ _ . .. ... ..= , ; : :: -> => <- # $ ? ~ ( ) [ ] { }";
+    let tokens = tokenize(input)
+        .into_iter()
+        .filter(|&i| i != Whitespace)
+        .collect::<Vec<_>>();
+    assert_eq!(
+        tokens,
+        &[
+            BinaryOperator(Plus),
+            BinaryOperator(Minus),
+            BinaryOperator(Star),
+            BinaryOperator(Slash),
+            BinaryOperator(Percent),
+            BinaryOperator(Caret),
+            Exclamation,
+            BinaryOperator(And),
+            BinaryOperator(Or),
+            DoubleAnd,
+            DoubleOr,
+            BinaryOperator(Shl),
+            BinaryOperator(Shr),
+            BinaryOperatorAssignment(Plus),
+            BinaryOperatorAssignment(Minus),
+            BinaryOperatorAssignment(Star),
+            BinaryOperatorAssignment(Slash),
+            BinaryOperatorAssignment(Percent),
+            BinaryOperatorAssignment(Caret),
+            BinaryOperatorAssignment(And),
+            BinaryOperatorAssignment(Or),
+            BinaryOperatorAssignment(Shl),
+            BinaryOperatorAssignment(Shr),
+            Equal,
+            DoubleEqual,
+            NotEqual,
+            GreaterThan,
+            LessThan,
+            GreaterEqual,
+            LessEqual,
+            At,
+            Underscore,
+            Dot,
+            DotDot,
+            DotDotDot,
+            DotDotEq,
+            Comma,
+            Semicolon,
+            Colon,
+            DoubleColon,
+            RightArrow,
+            FatArrow,
+            LeftArrow,
+            Sharp,
+            Dollar,
+            Question,
+            Tilde,
+            Left(Parenthesis),
+            Right(Parenthesis),
+            Left(Bracket),
+            Right(Bracket),
+            Left(Brace),
+            Right(Brace),
+        ]
+    );
+
+    // The longest token is taken
+    assert_eq!(tokenize("&&=||="), &[DoubleAnd, Equal, DoubleOr, Equal]);
+    assert_eq!(
+        tokenize("&&&|||"),
+        &[DoubleAnd, BinaryOperator(And), DoubleOr, BinaryOperator(Or)]
+    );
+    assert_eq!(
+        tokenize("x@_|_=>!~_x"),
+        &[
+            Identifier,
+            At,
+            Underscore,
+            BinaryOperator(Or),
+            Underscore,
+            FatArrow,
+            Exclamation,
+            Tilde,
+            Identifier,
+        ]
+    );
+    assert_eq!(tokenize("==>"), &[DoubleEqual, GreaterThan]);
+
+    // Shebang is only recognized at the start and is not an inner attribute
+    assert_eq!(
+        tokenize("#!/usr/bin/env run-cargo-script\nfn"),
+        &[Shebang, Whitespace, Keyword(Kw::Fn)]
+    );
+    assert_eq!(
+        tokenize("#![a]"),
+        &[
+            Sharp,
+            Exclamation,
+            Left(Bracket),
+            Identifier,
+            Right(Bracket)
+        ]
+    );
+    assert_eq!(
+        tokenize(" #!a"),
+        &[Whitespace, Sharp, Exclamation, Identifier]
+    );
+    // Whitespace and comments can be between `#!` and `[` of an inner attribute
+    for &(input, trivia) in &[
+        ("#! [a]", Whitespace),
+        ("#!\n[a]", Whitespace),
+        ("#!/*c*/[a]", Comment),
+        ("#!/* /* */ */[a]", Comment),
+        ("#!// c\n[a]", Comment),
+        ("#!//// c\n[a]", Comment),
+        ("#!/**/[a]", Comment),
+        ("#!/***/[a]", Comment),
+    ] {
+        assert_eq!(tokenize(input)[..3], [Sharp, Exclamation, trivia]);
+    }
+    assert_eq!(
+        tokenize("#!\n[a]"),
+        &[
+            Sharp,
+            Exclamation,
+            Whitespace,
+            Left(Bracket),
+            Identifier,
+            Right(Bracket)
+        ]
+    );
+    // Doc comments can't be there, so these are shebangs
+    for &input in &[
+        "#! /bin/sh\n[a]",
+        "#!/** c */[a]",
+        "#!/*! c */[a]",
+        "#!/* c",
+        "#!/// c\n[a]",
+        "#!//! c\n[a]",
+    ] {
+        assert_eq!(tokenize(input)[0], Shebang);
+    }
+    // The lookahead is linear in the length of the whitespace and comments
+    let long = format!("#!{}[a]", " /* c */\n".repeat(100_000));
+    let tokens = StrTokenizer::new(&long).map(|i| i.unwrap().token);
+    assert_eq!(tokens.take(2).collect::<Vec<_>>(), [Sharp, Exclamation]);
+}
+
+#[test]
+fn test_spacing() {
+    let spacings = |input: &str| {
+        Tokenizer::new(input.chars())
+            .spanned()
+            .map(|i| i.unwrap())
+            .filter(|i| i.token != Whitespace)
+            .map(|i| (i.token, i.spacing))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        spacings("a >> b"),
+        &[
+            (Identifier, Spacing::Alone),
+            (BinaryOperator(Shr), Spacing::Alone),
+            (Identifier, Spacing::Alone),
+        ]
+    );
+    assert_eq!(
+        spacings("&& & &"),
+        &[
+            (DoubleAnd, Spacing::Alone),
+            (BinaryOperator(And), Spacing::Alone),
+            (BinaryOperator(And), Spacing::Alone),
+        ]
+    );
+    assert_eq!(
+        spacings("x=>-1;//"),
+        &[
+            (Identifier, Spacing::Alone),
+            (FatArrow, Spacing::Joint),
+            (BinaryOperator(Minus), Spacing::Alone),
+            (INT, Spacing::Alone),
+            (Semicolon, Spacing::Alone),
+            (Comment, Spacing::Alone),
+        ]
+    );
+    assert_eq!(
+        spacings("#![_]"),
+        &[
+            (Sharp, Spacing::Joint),
+            (Exclamation, Spacing::Alone),
+            (Left(Bracket), Spacing::Alone),
+            (Underscore, Spacing::Alone),
+            (Right(Bracket), Spacing::Alone),
+        ]
+    );
+
+    // Closing generics are glued into a shift by the lexer and split by the parser
+    let tokens = Tokenizer::new("Vec<Vec<u8>>=".chars())
+        .spanned()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let shr = tokens[5];
+    assert_eq!(shr.token, BinaryOperatorAssignment(Shr));
+    let (first, rest) = shr.split().unwrap();
+    let (second, equal) = rest.split().unwrap();
+    assert!(equal.split().is_none());
+    let parts = [first, second, equal];
+    assert_eq!(
+        parts
+            .iter()
+            .map(|i| (i.token, i.text.as_str(), i.spacing))
+            .collect::<Vec<_>>(),
+        &[
+            (GreaterThan, ">", Spacing::Joint),
+            (GreaterThan, ">", Spacing::Joint),
+            (Equal, "=", Spacing::Alone),
+        ]
+    );
+    assert_eq!(
+        parts
+            .iter()
+            .map(|i| (i.span.start.offset, i.span.end.column))
+            .collect::<Vec<_>>(),
+        &[(10, 12), (11, 13), (12, 14)]
+    );
+    assert_eq!(DotDotEq.split(), Some((Dot, DotEq)));
+    assert_eq!(
+        RightArrow.split(),
+        Some((BinaryOperator(Minus), GreaterThan))
+    );
+    assert_eq!(Identifier.split(), None);
+}
+
+#[test]
+fn test_error_location() {
+    let error = Tokenizer::new("x\n  `".chars())
+        .find_map(|i| i.err())
+        .unwrap();
+    assert_eq!(error.to_string(), "unknown start of token: ` at 2:3");
+    assert_eq!(error.span.len(), 1);
+}
+
+#[test]
+fn test_spans() {
+    let spans = Tokenizer::new("let s = \"ы\";\n  x".chars())
+        .spanned()
+        .map(|i| {
+            let i = i.unwrap();
+            let Span { start, end } = i.span;
+            (
+                (start.offset, start.line, start.column),
+                (end.offset, end.line, end.column),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        spans,
+        &[
+            ((0, 1, 1), (3, 1, 4)),
+            ((3, 1, 4), (4, 1, 5)),
+            ((4, 1, 5), (5, 1, 6)),
+            ((5, 1, 6), (6, 1, 7)),
+            ((6, 1, 7), (7, 1, 8)),
+            ((7, 1, 8), (8, 1, 9)),
+            ((8, 1, 9), (12, 1, 12)),
+            ((12, 1, 12), (13, 1, 13)),
+            ((13, 1, 13), (16, 2, 3)),
+            ((16, 2, 3), (17, 2, 4)),
+        ][..]
+    );
+}
+
+#[test]
+fn test_text() {
+    let texts = Tokenizer::new("let x = r#\"a\"#;".chars())
+        .spanned()
+        .map(|i| i.unwrap().text.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(texts, ["let", " ", "x", " ", "=", " ", "r#\"a\"#", ";"]);
+    assert_eq!(Symbol::intern("x"), Symbol::intern("x"));
+    assert_ne!(Symbol::intern("x"), Symbol::intern("y"));
+}
+
+#[test]
+fn test_editions() {
+    let input = "async fn f(x: &dyn T) { try { gen } } r#match r#self c\"\\0\" cr#\"\"#";
+    let tokens = |edition| {
+        Tokenizer::new(input.chars())
+            .with_edition(edition)
+            .map(|i| i.map_err(|e| e.kind))
+            .filter(|i| i != &Ok(Whitespace))
+            .collect::<Vec<_>>()
+    };
+    let common = |async_: Token,
+                  dyn_: Token,
+                  try_: Token,
+                  gen: Token,
+                  strings: &[Result<Token, LexErrorKind>]| {
+        let mut tokens = vec![
+            Ok(async_),
+            Ok(Keyword(Kw::Fn)),
+            Ok(Identifier),
+            Ok(Left(Parenthesis)),
+            Ok(Identifier),
+            Ok(Colon),
+            Ok(BinaryOperator(And)),
+            Ok(dyn_),
+            Ok(Identifier),
+            Ok(Right(Parenthesis)),
+            Ok(Left(Brace)),
+            Ok(try_),
+            Ok(Left(Brace)),
+            Ok(gen),
+            Ok(Right(Brace)),
+            Ok(Right(Brace)),
+            Ok(Identifier),
+            Err(LexErrorKind::InvalidRawIdentifier("self")),
+            Ok(Identifier),
+        ];
+        tokens.extend_from_slice(strings);
+        tokens
+    };
+    let old_strings = &[
+        Ok(Identifier),
+        Ok(STR),
+        Ok(Identifier),
+        Ok(Sharp),
+        Ok(STR),
+        Ok(Sharp),
+    ];
+    let c_strings = &[
+        Err(LexErrorKind::NulInCStr),
+        Ok(LiteralStr {
+            kind: StrKind::C,
+            raw: None,
+        }),
+        Ok(LiteralStr {
+            kind: StrKind::C,
+            raw: Some(1),
+        }),
+    ];
+    assert_eq!(
+        tokens(Edition::Edition2015),
+        common(Identifier, Identifier, Identifier, Identifier, old_strings)
+    );
+    assert_eq!(
+        tokens(Edition::Edition2018),
+        common(
+            Keyword(Kw::Async),
+            Keyword(Kw::Dyn),
+            Keyword(Kw::Try),
+            Identifier,
+            old_strings
+        )
+    );
+    assert_eq!(
+        tokens(Edition::Edition2024),
+        common(
+            Keyword(Kw::Async),
+            Keyword(Kw::Dyn),
+            Keyword(Kw::Try),
+            Keyword(Kw::Gen),
+            c_strings
+        )
+    );
+}
+
+#[test]
+fn test_unicode_identifiers() {
+    assert_eq!(
+        tokenize("let größe = 'ы'; fn функция<'время>() {}"),
+        &[
+            Keyword(Kw::Let),
+            Whitespace,
+            Identifier,
+            Whitespace,
+            Equal,
+            Whitespace,
+            LiteralChar,
+            Semicolon,
+            Whitespace,
+            Keyword(Kw::Fn),
+            Whitespace,
+            Identifier,
+            LessThan,
+            IdentifierLifetime,
+            GreaterThan,
+            Left(Parenthesis),
+            Right(Parenthesis),
+            Whitespace,
+            Left(Brace),
+            Right(Brace),
+        ]
+    );
+    // Combining characters can't start an identifier
+    assert_eq!(
+        Tokenizer::new("\u{301}".chars()).next(),
+        Some(Err(LexError {
+            kind: LexErrorKind::UnexpectedChar('\u{301}'),
+            span: Span {
+                start: Position::start(),
+                end: Position {
+                    offset: 2,
+                    line: 1,
+                    column: 2,
+                },
+            },
+        }))
+    );
+}
+
+#[test]
+fn test_nfc() {
+    use unicode::to_nfc;
+
+    assert_eq!(to_nfc("A\u{30A}"), "\u{C5}");
+    assert_eq!(to_nfc("\u{212B}"), "\u{C5}");
+    assert_eq!(to_nfc("a\u{302}\u{323}"), "\u{1EAD}");
+    assert_eq!(to_nfc("a\u{323}\u{302}"), "\u{1EAD}");
+    assert_eq!(to_nfc("\u{1100}\u{1161}\u{11A8}"), "\u{AC01}");
+    assert_eq!(to_nfc("\u{AC01}"), "\u{AC01}");
+    assert_eq!(to_nfc("e\u{301}\u{301}"), "\u{E9}\u{301}");
+
+    let texts = |normalize| {
+        Tokenizer::new("cafe\u{301} caf\u{E9}".chars())
+            .with_nfc_identifiers(normalize)
+            .spanned()
+            .map(|i| i.unwrap().text)
+            .collect::<Vec<_>>()
+    };
+    assert_ne!(texts(false)[0], texts(false)[2]);
+    assert_eq!(texts(true)[0], texts(true)[2]);
+    assert_eq!(texts(true)[0].as_str(), "caf\u{E9}");
+}
+
+#[test]
+fn test_doc_comments() {
+    let input =
+        "/// outer\n//! inner\n//// plain\n// plain\n/** outer */ /*! inner */ /**/ /*** plain */";
+    let tokens = Tokenizer::new(input.chars())
+        .spanned()
+        .map(|i| i.unwrap())
+        .filter(|i| i.token != Whitespace)
+        .map(|i| (i.token, i.doc()))
+        .collect::<Vec<_>>();
+    let doc = |style, kind| DocComment { style, kind };
+    assert_eq!(
+        tokens,
+        &[
+            (doc(DocStyle::Outer, CommentKind::Line), Some(" outer")),
+            (doc(DocStyle::Inner, CommentKind::Line), Some(" inner")),
+            (Comment, None),
+            (Comment, None),
+            (doc(DocStyle::Outer, CommentKind::Block), Some(" outer ")),
+            (doc(DocStyle::Inner, CommentKind::Block), Some(" inner ")),
+            (Comment, None),
+            (Comment, None),
+        ]
+    );
+
+    // Lines continuing a doc comment have only the markers they contain
+    let mut state = LexState::default();
+    let mut docs = Vec::new();
+    for line in "/** a\n*/ x\n/*! é\nçà\n b */".split('\n') {
+        let mut lexemes = StrTokenizer::new(line).with_state(state);
+        docs.extend(lexemes.by_ref().filter_map(|i| i.unwrap().doc()));
+        state = lexemes.state();
+    }
+    assert_eq!(docs, &[" a", "", " é", "çà", " b "]);
+}
+
+#[test]
+fn test_nested_comments() {
+    assert_eq!(
+        tokenize("/* a /* b */ c */1/*/**/*/"),
+        &[Comment, INT, Comment]
+    );
+    assert_eq!(
+        tokenize("/** a /* b */ */"),
+        &[DocComment {
+            style: DocStyle::Outer,
+            kind: CommentKind::Block,
+        }]
+    );
+
+    let errors = Tokenizer::new("x /* a /* b */\n /* c".chars())
+        .filter_map(|i| i.err())
+        .collect::<Vec<_>>();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "unterminated block comment (nested comment at 2:2 is not closed) at 1:3"
+    );
+    assert_eq!(errors[0].span.start.offset, 2);
+}
+
+/// Short description of token trees, e.g. `(a [b])`
+fn describe_trees(trees: &[token_tree::TokenTree]) -> String {
+    use token_tree::TokenTree;
+
+    trees
+        .iter()
+        .map(|i| match *i {
+            TokenTree::Token(token) => token.text.to_string(),
+            TokenTree::Group(ref group) => format!(
+                "{}{}{}",
+                group.delimiter.open(),
+                describe_trees(&group.stream),
+                group
+                    .close
+                    .map_or(String::new(), |_| { group.delimiter.close().to_string() })
+            ),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn test_token_trees() {
+    use token_tree::{build, TreeError};
+
+    let trees = |input: &str| {
+        let (trees, errors) = build(Tokenizer::new(input.chars()).spanned());
+        (
+            describe_trees(&trees),
+            errors.iter().map(|i| i.to_string()).collect::<Vec<_>>(),
+        )
+    };
+
+    assert_eq!(
+        trees("fn f(x: [u8; 2]) { /* c */ x }"),
+        ("fn f (x : [u8 ; 2]) {x}".to_string(), vec![])
+    );
+    assert_eq!(
+        trees("{ (a ] }"),
+        (
+            "{(a}".to_string(),
+            vec!["mismatched closing delimiter `]` at 1:6 for `(` at 1:3".to_string()]
+        )
+    );
+    assert_eq!(
+        trees("(a [b) c"),
+        (
+            "(a [b) c".to_string(),
+            vec!["mismatched closing delimiter `)` at 1:6 for `[` at 1:4".to_string()]
+        )
+    );
+    assert_eq!(
+        trees("a) {b\n"),
+        (
+            "a {b".to_string(),
+            vec![
+                "unexpected closing delimiter `)` at 1:2".to_string(),
+                "unclosed delimiter `{` at 1:4, the input ends at 2:1".to_string(),
+            ]
+        )
+    );
+    assert_eq!(
+        trees("(]] a"),
+        (
+            "(a".to_string(),
+            vec![
+                "mismatched closing delimiter `]` at 1:2 for `(` at 1:1".to_string(),
+                "unexpected closing delimiter `]` at 1:3".to_string(),
+                "unclosed delimiter `(` at 1:1, the input ends at 1:6".to_string(),
+            ]
+        )
+    );
+
+    let (_, errors) = build(Tokenizer::new("(`)".chars()).spanned());
+    match errors[..] {
+        [TreeError::Lex(error)] => assert_eq!(error.kind, LexErrorKind::UnexpectedChar('`')),
+        _ => panic!("Unexpected errors {:?}", errors),
+    }
+}
+
+#[test]
+fn test_json() {
+    let mut out = Vec::new();
+    for token in Tokenizer::new("0u8 \"\t\\\"\u{1}é\"/**/`".chars()).spanned() {
+        match token {
+            Ok(token) => json::write_token(&mut out, Some("a\\b.rs"), &token).unwrap(),
+            Err(error) => json::write_error(&mut out, None, &error).unwrap(),
+        }
+        out.push(b'\n');
+    }
+    let span = |start: (usize, usize), end: (usize, usize)| {
+        format!(
+            "{{\"start\":{{\"offset\":{},\"line\":1,\"column\":{}}},\"end\":{{\"offset\":{},\"line\":1,\"column\":{}}}}}",
+            start.0, start.1, end.0, end.1
+        )
+    };
+    let expected = [
+        format!(
+            r#"{{"file":"a\\b.rs","kind":"LiteralInt","subkind":"Decimal","suffix":"u8","spacing":"Alone","text":"0u8","span":{}}}"#,
+            span((0, 1), (3, 4))
+        ),
+        format!(
+            r#"{{"file":"a\\b.rs","kind":"Whitespace","subkind":null,"spacing":"Alone","text":" ","span":{}}}"#,
+            span((3, 4), (4, 5))
+        ),
+        format!(
+            r#"{{"file":"a\\b.rs","kind":"LiteralStr","subkind":"Str","raw":null,"spacing":"Alone","text":"\"\t\\\"\u0001é\"","span":{}}}"#,
+            span((4, 5), (12, 12))
+        ),
+        format!(
+            r#"{{"file":"a\\b.rs","kind":"Comment","subkind":null,"spacing":"Alone","text":"/**/","span":{}}}"#,
+            span((12, 12), (16, 16))
+        ),
+        format!(
+            r#"{{"error":"UnexpectedChar","message":"unknown start of token: `","span":{}}}"#,
+            span((16, 16), (17, 17))
+        ),
+    ];
+    assert_eq!(String::from_utf8(out).unwrap(), expected.join("\n") + "\n");
+}
+
+#[test]
+fn test_lossless() {
+    let input = "a € `\u{feff}";
+    let tokens = Tokenizer::new(input.chars())
+        .with_lossless(true)
+        .with_nfc_identifiers(true)
+        .spanned()
+        .collect::<Vec<_>>();
+    let kinds = tokens
+        .iter()
+        .map(|i| i.map(|i| i.token).map_err(|e| e.kind))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        kinds,
+        &[
+            Ok(Identifier),
+            Ok(Whitespace),
+            Err(LexErrorKind::UnexpectedChar('€')),
+            Ok(Unknown),
+            Ok(Whitespace),
+            Err(LexErrorKind::UnexpectedChar('`')),
+            Ok(Unknown),
+            Err(LexErrorKind::UnexpectedChar('\u{feff}')),
+            Ok(Unknown),
+        ]
+    );
+    let text = tokens
+        .iter()
+        .filter_map(|i| i.ok())
+        .map(|i| i.text.as_str())
+        .collect::<String>();
+    assert_eq!(text, input);
+
+    // Identifiers are not normalized
+    let token = Tokenizer::new("e\u{301}".chars())
+        .with_nfc_identifiers(true)
+        .with_lossless(true)
+        .spanned()
+        .next();
+    assert_eq!(token.unwrap().unwrap().text.as_str(), "e\u{301}");
+}
+
+#[test]
+fn test_str_tokenizer() {
+    let source = String::from("/// doc\nfn café() -> u8 { 0x_ }");
+    let lexemes = StrTokenizer::new(&source)
+        .with_edition(Edition::Edition2018)
+        .filter_map(|i| i.ok())
+        .filter(|i| i.token != Whitespace)
+        .collect::<Vec<_>>();
+    // Texts are slices of the source
+    let name = lexemes[2];
+    assert_eq!(name.text, "café");
+    assert_eq!(
+        name.text.as_ptr(),
+        source[name.span.start.offset..].as_ptr()
+    );
+    assert_eq!(lexemes[0].doc(), Some(" doc"));
+    assert_eq!(
+        lexemes
+            .iter()
+            .map(|i| (i.token, i.text))
+            .collect::<Vec<_>>(),
+        &[
+            (
+                DocComment {
+                    style: DocStyle::Outer,
+                    kind: CommentKind::Line
+                },
+                "/// doc"
+            ),
+            (Keyword(Kw::Fn), "fn"),
+            (Identifier, "café"),
+            (Left(Parenthesis), "("),
+            (Right(Parenthesis), ")"),
+            (RightArrow, "->"),
+            (Identifier, "u8"),
+            (Left(Brace), "{"),
+            (
+                LiteralInt {
+                    base: Base::Hexadecimal,
+                    suffix: None
+                },
+                "0x_"
+            ),
+            (Right(Brace), "}"),
+        ]
+    );
+    assert!(StrTokenizer::from_bytes(b"fn \xff").is_err());
+
+    // Runs of characters skipped at once end at the same positions and errors as one by one
+    let source = "é_1 x\t\n  1_000 0b12 0o8 \"a\nbé\\q\" b\"é\" c\"\0\" br#\"é\n\"# \
+        /* é\n* / */ // ü\n'a' r#\"a\rb\"# 'b\n\u{1F600}_x";
+    let file = SourceFile::new("", source);
+    let spans = |tokens: Vec<Result<(Token, Span), LexError>>| {
+        for span in tokens.iter().map(|i| i.map_or_else(|i| i.span, |i| i.1)) {
+            assert_eq!(span.start, file.position(span.start.offset));
+            assert_eq!(span.end, file.position(span.end.offset));
+        }
+        tokens
+    };
+    let chars = Tokenizer::new(source.chars())
+        .with_edition(Edition::Edition2021)
+        .spanned()
+        .map(|i| i.map(|i| (i.token, i.span)))
+        .collect();
+    let str = StrTokenizer::new(source)
+        .with_edition(Edition::Edition2021)
+        .map(|i| i.map(|i| (i.token, i.span)))
+        .collect();
+    let str = spans(str);
+    assert_eq!(str, spans(chars));
+    let errors = str.iter().filter_map(|i| i.err()).map(|i| i.kind);
+    assert_eq!(
+        errors.collect::<Vec<_>>(),
+        [
+            LexErrorKind::InvalidDigit('2'),
+            LexErrorKind::InvalidDigit('8'),
+            LexErrorKind::NoDigits,
+            LexErrorKind::UnknownEscape('q'),
+            LexErrorKind::NonAsciiByte('é'),
+            LexErrorKind::NulInCStr,
+            LexErrorKind::NonAsciiByte('é'),
+            LexErrorKind::UnescapedChar('\r'),
+            LexErrorKind::UnexpectedChar('😀'),
+        ]
+    );
+}
+
+#[test]
+fn test_reader() {
+    use std::io::BufReader;
+
+    let source = "fn 𝔰() { \"ключ\" } // 日本";
+    // Buffers of every size split multibyte characters differently
+    for capacity in 1..8 {
+        let reader = BufReader::with_capacity(capacity, source.as_bytes());
+        let mut tokenizer = Tokenizer::from_reader(reader);
+        let mut lexemes = Vec::new();
+        while let Some(lexeme) = tokenizer.next_lexeme() {
+            let lexeme = lexeme.unwrap();
+            lexemes.push((lexeme.token, lexeme.text.to_string(), lexeme.span));
+        }
+        assert!(tokenizer.get_ref().error().is_none());
+        let expected = StrTokenizer::new(source)
+            .map(|i| i.unwrap())
+            .map(|i| (i.token, i.text.to_string(), i.span))
+            .collect::<Vec<_>>();
+        assert_eq!(lexemes, expected);
+
+        let reader = BufReader::with_capacity(capacity, source.as_bytes());
+        let tokens = Tokenizer::from_reader(reader)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let expected = expected.iter().map(|i| i.0).collect::<Vec<_>>();
+        assert_eq!(tokens, expected);
+    }
+
+    // Large generated sources are streamed through the iterator
+    let source = (0..10_000)
+        .map(|i| format!("let x{} = {};\n", i, i))
+        .collect::<String>();
+    let reader = BufReader::with_capacity(64, source.as_bytes());
+    let mut tokenizer = Tokenizer::from_reader(reader);
+    assert_eq!(tokenizer.by_ref().filter(|i| i.is_ok()).count(), 10_000 * 9);
+    assert!(tokenizer.get_ref().error().is_none());
+
+    // Invalid and truncated sequences stop the reading
+    for &(bytes, offset) in &[
+        (&b"ab\xffc"[..], 2),
+        (b"a\xe6\x97", 1),
+        (b"\xed\xa0\x80", 0),
+    ] {
+        let mut chars = ReadChars::new(BufReader::with_capacity(2, bytes));
+        assert_eq!(chars.by_ref().count(), offset);
+        let error = chars.error().unwrap();
+        assert_eq!(error.kind(), ::std::io::ErrorKind::InvalidData);
+        assert_eq!(
+            error.to_string(),
+            format!("invalid UTF-8 sequence at byte {}", offset)
+        );
+        assert_eq!(chars.next(), None);
+    }
+}
+
+#[test]
+fn test_trivia() {
+    let input =
+        "#!shebang\n// leading\nfn f() { // trailing\n    x /* c */ } /* a\nb */\n\n// end\n";
+    let (stream, errors) = trivia::attach(Tokenizer::new(input.chars()).spanned());
+    assert_eq!(errors, &[]);
+    assert_eq!(stream.text(), input);
+    let texts =
+        |tokens: &[SpannedToken]| tokens.iter().map(|i| i.text.as_str()).collect::<Vec<_>>();
+    let attached = stream
+        .tokens
+        .iter()
+        .map(|i| (texts(&i.leading), i.token.text.as_str(), texts(&i.trailing)))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        attached,
+        &[
+            (vec!["#!shebang", "\n", "// leading", "\n"], "fn", vec![" "]),
+            (vec![], "f", vec![]),
+            (vec![], "(", vec![]),
+            (vec![], ")", vec![" "]),
+            (vec![], "{", vec![" ", "// trailing"]),
+            (vec!["\n    "], "x", vec![" ", "/* c */", " "]),
+            (vec![], "}", vec![" "]),
+        ]
+    );
+    assert_eq!(texts(&stream.end), &["/* a\nb */", "\n\n", "// end", "\n"]);
+    let x = &stream.tokens[5];
+    assert_eq!(x.full_span().start.offset, input.find("\n    x").unwrap());
+    assert_eq!(x.full_span().end.offset, input.find("}").unwrap());
+
+    let (stream, _) = trivia::attach(Tokenizer::new(" // only".chars()).spanned());
+    assert_eq!(stream.tokens, &[]);
+    assert_eq!(texts(&stream.end), &[" ", "// only"]);
+}
+
+#[test]
+fn test_line_state() {
+    let source = "#!/bin/sh\n#!x\n/*! a /* b\nc */\nd */ x.\n0.1 b\"\\\ne\" br##\"\n\"#\n\"##";
+    let mut state = LexState::default();
+    let mut lines = Vec::new();
+    for line in source.split('\n') {
+        let mut lexemes = StrTokenizer::new(line).with_state(state);
+        let tokens = lexemes
+            .by_ref()
+            .map(|i| i.map(|i| (i.token, i.text)))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        state = lexemes.state();
+        lines.push((tokens, state));
+    }
+
+    let normal = LexState::Normal { after_dot: false };
+    let doc = DocComment {
+        style: DocStyle::Inner,
+        kind: CommentKind::Block,
+    };
+    let comment = |depth| LexState::BlockComment {
+        depth,
+        style: Some(DocStyle::Inner),
+    };
+    let bytes = LiteralStr {
+        kind: StrKind::Byte,
+        raw: None,
+    };
+    let raw = LiteralStr {
+        kind: StrKind::Byte,
+        raw: Some(2),
+    };
+    let raw_state = LexState::Str {
+        kind: StrKind::Byte,
+        raw: Some(2),
+    };
+    assert_eq!(
+        lines,
+        &[
+            (vec![(Shebang, "#!/bin/sh")], normal),
+            (
+                vec![(Sharp, "#"), (Exclamation, "!"), (Identifier, "x")],
+                normal
+            ),
+            (vec![(doc, "/*! a /* b")], comment(2)),
+            (vec![(doc, "c */")], comment(1)),
+            (
+                vec![
+                    (doc, "d */"),
+                    (Whitespace, " "),
+                    (Identifier, "x"),
+                    (Dot, ".")
+                ],
+                LexState::Normal { after_dot: true }
+            ),
+            (
+                vec![
+                    (INT, "0"),
+                    (Dot, "."),
+                    (INT, "1"),
+                    (Whitespace, " "),
+                    (bytes, "b\"\\")
+                ],
+                LexState::Str {
+                    kind: StrKind::Byte,
+                    raw: None
+                }
+            ),
+            (
+                vec![(bytes, "e\""), (Whitespace, " "), (raw, "br##\"")],
+                raw_state
+            ),
+            (vec![(raw, "\"#")], raw_state),
+            (vec![(raw, "\"##")], normal),
+        ]
+    );
+
+    // Lines keeping their CRLF line breaks, with a doc comment continued over them
+    let source = "/** a\r\n b\r\n */ x\r\n\"s\r\n\"\r\n";
+    let mut state = LexState::default();
+    let mut lines = Vec::new();
+    for line in source.split_inclusive('\n') {
+        let mut lexemes = StrTokenizer::new(line).with_state(state);
+        let tokens = lexemes
+            .by_ref()
+            .map(|i| i.map(|i| (i.token, i.text, i.doc())))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        state = lexemes.state();
+        lines.push((tokens, state));
+    }
+    let doc = DocComment {
+        style: DocStyle::Outer,
+        kind: CommentKind::Block,
+    };
+    let comment = LexState::BlockComment {
+        depth: 1,
+        style: Some(DocStyle::Outer),
+    };
+    let str_state = LexState::Str {
+        kind: StrKind::Str,
+        raw: None,
+    };
+    assert_eq!(
+        lines,
+        &[
+            (vec![(doc, "/** a\r\n", Some(" a\r\n"))], comment),
+            (vec![(doc, " b\r\n", Some(" b\r\n"))], comment),
+            (
+                vec![
+                    (doc, " */", Some(" ")),
+                    (Whitespace, " ", None),
+                    (Identifier, "x", None),
+                    (Whitespace, "\r\n", None)
+                ],
+                normal
+            ),
+            (vec![(STR, "\"s\r\n", None)], str_state),
+            (vec![(STR, "\"", None), (Whitespace, "\r\n", None)], normal),
+        ]
+    );
+
+    // Without a state the source is whole, so open literals are still reported
+    let errors = StrTokenizer::new("\"a")
+        .filter_map(Result::err)
+        .map(|i| i.kind)
+        .collect::<Vec<_>>();
+    assert_eq!(errors, &[LexErrorKind::UnterminatedStr]);
+
+    let lex = |source: &str, by_lines: bool| {
+        let mut state = LexState::default();
+        let mut lexemes = Vec::new();
+        let lines = match by_lines {
+            true => source.split_inclusive('\n').collect(),
+            false => vec![source],
+        };
+        for line in lines {
+            let mut tokens = StrTokenizer::new(line).with_state(state);
+            lexemes.extend(
+                tokens
+                    .by_ref()
+                    .map(|i| i.map(|i| i.token).map_err(|i| i.kind)),
+            );
+            state = tokens.state();
+        }
+        lexemes
+    };
+    // Malformed char literals end at the line break
+    for source in &["'\\\nx", "'\nc'", "b'\n'", "'ab\nc'", "'a\\\r\n'"] {
+        assert_eq!(lex(source, true), lex(source, false), "{:?}", source);
+    }
+    assert_eq!(
+        lex("'\\\nx", false),
+        &[
+            Err(LexErrorKind::UnterminatedChar),
+            Ok(LiteralChar),
+            Ok(Whitespace),
+            Ok(Identifier)
+        ]
+    );
+    // The documented exceptions
+    assert_eq!(lex("#!\n[a]", false)[..2], [Ok(Sharp), Ok(Exclamation)]);
+    assert_eq!(lex("#!\n[a]", true)[..2], [Ok(Shebang), Ok(Whitespace)]);
+    assert_eq!(lex("\"\\\n \r\"", false), &[Ok(STR)]);
+    assert_eq!(
+        lex("\"\\\n \r\"", true),
+        &[Ok(STR), Err(LexErrorKind::UnescapedChar('\r')), Ok(STR)]
+    );
+}
+
+#[test]
+fn test_source_file() {
+    let file = SourceFile::new("a.rs", "ab\r\n\tcé\n\nd");
+    assert_eq!(file.name(), "a.rs");
+    assert_eq!(file.line_count(), 4);
+    assert_eq!(file.line(1), Some("ab"));
+    assert_eq!(file.line(2), Some("\tcé"));
+    assert_eq!(file.line(3), Some(""));
+    assert_eq!(file.line(4), Some("d"));
+    assert_eq!(file.line(0), None);
+    assert_eq!(file.line(5), None);
+    assert_eq!(file.line_start(2), Some(4));
+    assert_eq!(file.lookup_line(3), 1);
+    assert_eq!(file.lookup_line(4), 2);
+    for token in file.tokens() {
+        let span = token.unwrap().span;
+        assert_eq!(file.position(span.start.offset), span.start);
+        assert_eq!(file.position(span.end.offset), span.end);
+        assert_eq!(file.text(span), token.unwrap().text);
+    }
+}
+
+#[test]
+fn test_source_map() {
+    let mut map = SourceMap::new();
+    let a = map.add_file("a.rs", "fn é() {\n    \"😀\" }");
+    let empty = map.add_file("empty.rs", "");
+    let b = map.add_file("b.rs", "x");
+    assert_eq!(
+        map.files().map(|(_, i)| i.name()).collect::<Vec<_>>(),
+        &["a.rs", "empty.rs", "b.rs"]
+    );
+
+    // Every position of every file has its own global position
+    let mut last = None;
+    for (id, file) in map.files() {
+        for offset in (0..=file.src().len()).filter(|&i| file.src().is_char_boundary(i)) {
+            let pos = map.global_pos(id, offset);
+            assert!(last < Some(pos));
+            last = Some(pos);
+            assert_eq!(map.lookup(pos), Some((id, file.position(offset))));
+        }
+    }
+    assert_eq!(map.lookup_file(map.global_pos(empty, 0)), Some(empty));
+    assert_eq!(map.lookup_file(map.global_pos(b, 2)), None);
+
+    let file = map.file(a);
+    for token in file.tokens() {
+        let span = token.unwrap().span;
+        assert_eq!(map.lookup_span(map.global_span(a, span)), Some((a, span)));
+    }
+    let span = GlobalSpan {
+        start: map.global_pos(a, 0),
+        end: map.global_pos(b, 0),
+    };
+    assert_eq!(map.lookup_span(span), None);
+
+    // Columns in bytes and UTF-16 code units
+    let smile = file.src().find('😀').unwrap();
+    let close = file.src().find('}').unwrap();
+    assert_eq!(file.position(close).column, 9);
+    assert_eq!(file.byte_column(close), 12);
+    assert_eq!(file.utf16_column(close), 10);
+    assert_eq!(file.utf16_column(file.src().find('(').unwrap()), 5);
+    assert_eq!(file.utf16_offset(2, 10), Some(close));
+    assert_eq!(file.utf16_offset(2, 6), Some(smile));
+    assert_eq!(file.utf16_offset(2, 7), None);
+    assert_eq!(file.utf16_offset(2, 11), Some(file.src().len()));
+    assert_eq!(file.utf16_offset(2, 12), None);
+    assert_eq!(file.utf16_offset(3, 1), None);
+}
+
+#[test]
+fn test_diagnostic() {
+    let file = SourceFile::new("main.rs", "fn f() {\n\tb\"\\u{e9}\"; /* a\n/* b\n\n");
+    let rendered = file
+        .tokens()
+        .filter_map(Result::err)
+        .map(|i| Diagnostic::from(&i).render(&file, false))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        rendered,
+        &[
+            "error: unicode escape in byte string
+ --> main.rs:2:4
+  |
+2 |     b\"\\u{e9}\"; /* a
+  |       ^^^^^^ unicode escape in byte string
+  |
+  = help: unicode escape sequences cannot be used as a byte or in a byte string
+",
+            "error: unterminated block comment (nested comment at 3:1 is not closed)
+ --> main.rs:2:13
+  |
+2 |     b\"\\u{e9}\"; /* a
+  |                ^^ unterminated block comment
+3 | /* b
+  | -- ...as last nested comment starts here, maybe you want to close this instead?
+",
+        ]
+    );
+
+    // Multiline labels are shown on their first and last lines
+    let diagnostic = Diagnostic::new(Level::Warning, "block")
+        .with_label(
+            Span {
+                start: file.position(7),
+                end: file.position(file.src().len()),
+            },
+            "body",
+        )
+        .with_note("note");
+    assert_eq!(
+        diagnostic.render(&file, false),
+        "warning: block
+ --> main.rs:1:8
+  |
+1 | fn f() {
+  |        ^
+...
+5 |
+  | ^ body
+  |
+  = note: note
+"
+    );
+    assert_eq!(
+        diagnostic.render(&SourceFile::new("<stdin>", ""), true),
+        "\x1b[1;33mwarning\x1b[0m\x1b[1m: block\x1b[0m
+\x1b[1;34m--> \x1b[0m<stdin>:1:8
+\x1b[1;34m =\x1b[0m \x1b[1mnote\x1b[0m: note
+"
+    );
+
+    // Lines can be given one by one, labels on the missing ones are not shown
+    let write_lines = |first: usize| {
+        let mut out = Vec::new();
+        diagnostic
+            .write_lines(
+                &mut out,
+                "main.rs",
+                |i| file.line(i).filter(|_| i >= first),
+                false,
+            )
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    };
+    assert_eq!(write_lines(1), diagnostic.render(&file, false));
+    assert_eq!(
+        write_lines(2),
+        "warning: block\n--> main.rs:1:8\n = note: note\n"
+    );
+}
+
+#[test]
+fn test_highlight() {
+    for &class in Class::ALL {
+        assert_eq!(Class::from_name(class.name()), Some(class));
+    }
+    let theme = Theme::parse(
+        "# comment\n\nkeyword = bold magenta\n  comment=#FF8000 on bright-black\nunknown = none\n",
+    )
+    .unwrap();
+    assert_eq!(
+        theme.highlight("fn f() {} /* a\nb */ `", Edition::default()),
+        "\x1b[1;35mfn\x1b[0m f() {} \x1b[38;2;255;128;0;100m/* a\x1b[0m\n\
+         \x1b[38;2;255;128;0;100mb */\x1b[0m `"
+    );
+    assert_eq!(
+        Theme::parse("")
+            .unwrap()
+            .highlight("x + 1", Edition::default()),
+        "x + 1"
+    );
+    assert_eq!(
+        Theme::default(),
+        Theme::parse(highlight::DEFAULT_THEME).unwrap()
+    );
+
+    let error = |text| Theme::parse(text).unwrap_err().to_string();
+    assert_eq!(
+        error("keyword = red\nstring"),
+        "expected `class = style` at line 2"
+    );
+    assert_eq!(
+        error("keywords = red"),
+        "unknown class `keywords` at line 1"
+    );
+    assert_eq!(
+        error("string = purple"),
+        "unknown colour `purple` at line 1"
+    );
+    assert_eq!(
+        error("string = red green"),
+        "unexpected `green` after the colour at line 1"
+    );
+    assert_eq!(
+        error("string = #12345g"),
+        "unknown colour `#12345g` at line 1"
+    );
+    assert_eq!(
+        error("string = red on"),
+        "missing background colour after `on` at line 1"
+    );
+}
+
+#[test]
+fn test_html() {
+    use html::{self, HtmlWriter};
+
+    let mut out = Vec::new();
+    html::write_str(&mut out, "<a href=\"x\">'&'</a>").unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "&lt;a href=&quot;x&quot;&gt;&#39;&amp;&#39;&lt;/a&gt;"
+    );
+
+    assert_eq!(
+        html::to_html("'a' /* <\n\n> */ ¤", Edition::default(), false),
+        "<pre class=\"rust\"><code><span class=\"char\">&#39;a&#39;</span> \
+         <span class=\"comment\">/* &lt;</span>\n\
+         \n\
+         <span class=\"comment\">&gt; */</span> <span class=\"unknown\">¤</span></code></pre>\n"
+    );
+    assert_eq!(
+        html::to_html("x\n\n", Edition::default(), true),
+        "<pre class=\"rust\"><code>\
+         <span class=\"line\" id=\"L1\"><a class=\"line-number\" href=\"#L1\">1</a>\
+         <span class=\"identifier\">x</span></span>\n\
+         <span class=\"line\" id=\"L2\"><a class=\"line-number\" href=\"#L2\">2</a></span>\n\
+         </code></pre>\n"
+    );
+    assert_eq!(
+        html::to_html("", Edition::default(), true),
+        "<pre class=\"rust\"><code></code></pre>\n"
+    );
+
+    // Lines are restarted for every source
+    let mut html = HtmlWriter::new().with_line_numbers(true);
+    let mut out = Vec::new();
+    for src in &["a\nb", "c"] {
+        for token in StrTokenizer::new(src).with_lossless(true) {
+            html.write_lexeme(&mut out, &token.unwrap()).unwrap();
+        }
+        html.finish(&mut out).unwrap();
+    }
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "<pre class=\"rust\"><code>\
+         <span class=\"line\"><span class=\"line-number\">1</span>\
+         <span class=\"identifier\">a</span></span>\n\
+         <span class=\"line\"><span class=\"line-number\">2</span>\
+         <span class=\"identifier\">b</span></span></code></pre>\n\
+         <pre class=\"rust\"><code>\
+         <span class=\"line\"><span class=\"line-number\">1</span>\
+         <span class=\"identifier\">c</span></span></code></pre>\n"
+    );
+}
+
+#[test]
+fn test_semantic_tokens() {
+    use semantic_tokens::{self, SemanticTokensEdit, TokenModifier, TokenType};
+
+    for (i, &token_type) in TokenType::ALL.iter().enumerate() {
+        assert_eq!(token_type.index(), i as u32);
+    }
+    assert_eq!(TokenType::TypeParameter.as_str(), "typeParameter");
+    assert_eq!(TokenModifier::Documentation.bit(), 1);
+
+    // Columns and lengths are in UTF-16 code units, multiline tokens are split by lines
+    let file = SourceFile::new("main.rs", "/// 𝄞\r\nfn f<'a>() {\n    \"x\r\n\ry\" }\n");
+    let data = semantic_tokens::full(&file, Edition::default());
+    assert_eq!(
+        data,
+        [
+            0, 0, 6, 5, 1, // /// 𝄞
+            1, 0, 2, 0, 0, // fn
+            0, 3, 1, 1, 0, // f
+            0, 1, 1, 6, 0, // <
+            0, 1, 2, 2, 0, // 'a
+            0, 2, 1, 6, 0, // >
+            1, 4, 2, 3, 0, // "x
+            1, 0, 3, 3, 0, // \ry"
+        ]
+    );
+    assert_eq!(semantic_tokens::diff(&data, &data), []);
+
+    let old = semantic_tokens::full(&SourceFile::new("", "a b c d"), Edition::default());
+    let new = semantic_tokens::full(&SourceFile::new("", "a bb d"), Edition::default());
+    assert_eq!(
+        semantic_tokens::diff(&old, &new),
+        [SemanticTokensEdit {
+            start: 5,
+            delete_count: 15,
+            data: vec![0, 2, 2, 1, 0, 0, 3, 1, 1, 0],
+        }]
+    );
+    // The common prefix and suffix don't overlap when tokens repeat
+    let old = semantic_tokens::full(&SourceFile::new("", "a a"), Edition::default());
+    let new = semantic_tokens::full(&SourceFile::new("", "a a a"), Edition::default());
+    assert_eq!(
+        semantic_tokens::diff(&old, &new),
+        [SemanticTokensEdit {
+            start: 10,
+            delete_count: 0,
+            data: vec![0, 2, 1, 1, 0],
+        }]
+    );
+}
+
+#[test]
+fn test_incremental() {
+    let sources = [
+        "fn f() { let s = \"a\\\"b\"; }",
+        "x.0.1 /* a /* b */ */ 1..2",
+        "#!/bin/sh\nr#\"é\"# 'a' 'b",
+        "#!\n/* c */ x",
+        "x /* /* a */ /* b",
+        "",
+    ];
+    let texts = ["", "\"", "/*", "*/", "5", ".", "\n", "é", "b'", "#!", "["];
+    // Every edit of the sources must give the same tokens and errors as lexing the new source
+    for source in &sources {
+        let bounds = (0..=source.len())
+            .filter(|&i| source.is_char_boundary(i))
+            .collect::<Vec<_>>();
+        for (i, &start) in bounds.iter().enumerate() {
+            for &end in &bounds[i..] {
+                for text in &texts {
+                    let edited = format!("{}{}{}", &source[..start], text, &source[end..]);
+                    let mut tokens = TokenList::new(source, Edition::Edition2021);
+                    tokens.edit(&TextEdit {
+                        range: start..end,
+                        text,
+                    });
+                    assert_eq!(
+                        tokens,
+                        TokenList::new(&edited, Edition::Edition2021),
+                        "{:?} edited into {:?}",
+                        source,
+                        edited
+                    );
+                }
+            }
+        }
+    }
+
+    // Only the tokens near the edit are lexed again, unless it opens or closes a string
+    let source = "fn f() {\n    let x = 1;\n}\n".repeat(100);
+    let mut tokens = TokenList::new(&source, Edition::default());
+    let offset = source.find('x').unwrap();
+    let relexed = tokens.edit(&TextEdit {
+        range: offset..offset + 1,
+        text: "y",
+    });
+    assert_eq!(relexed, 8..11);
+    let relexed = tokens.edit(&TextEdit {
+        range: offset..offset,
+        text: "\"",
+    });
+    assert_eq!(relexed.end, tokens.tokens().len());
+    let relexed = tokens.edit(&TextEdit {
+        range: offset + 3..offset + 3,
+        text: "\"",
+    });
+    assert_eq!(relexed.end, tokens.tokens().len());
+    assert_eq!(tokens.tokens()[10].text.as_str(), "\"y \"");
+    assert_eq!(tokens.text(), source.replacen("x =", "\"y \"=", 1));
+}
+
 fn test_on_folder(folder_name: &str) {
     use std::fs::{read_dir, read_to_string};
 
@@ -260,7 +1967,45 @@ fn test_on_folder(folder_name: &str) {
     {
         println!("Tokenizing file {:?}", entry);
         let contents = read_to_string(entry).unwrap();
-        let tokens = tokenize(&contents);
+        let tokens = Tokenizer::new(contents.chars())
+            .spanned()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        // Spans of consecutive tokens must cover the whole source without gaps
+        let mut offset = 0;
+        for token in &tokens {
+            assert_eq!(token.span.start.offset, offset);
+            offset = token.span.end.offset;
+        }
+        assert_eq!(offset, contents.len());
+        // Delimiters in the sources are balanced
+        let (_, errors) = token_tree::build(tokens.iter().cloned().map(Ok));
+        assert_eq!(errors, &[]);
+        // Texts of the tokens must reproduce the source, also after attaching the trivia
+        let text = tokens.iter().map(|i| i.text.as_str()).collect::<String>();
+        assert_eq!(text, contents);
+        let (stream, _) = trivia::attach(tokens.iter().cloned().map(Ok));
+        assert_eq!(stream.text(), contents);
+        // Scanning the string directly gives the same tokens with borrowed texts
+        let lexemes = StrTokenizer::from_bytes(contents.as_bytes())
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let spanned = lexemes.iter().map(|i| i.to_spanned()).collect::<Vec<_>>();
+        assert_eq!(spanned, tokens);
+        // Lexing line by line gives the same tokens, except they are split at line breaks
+        let mut state = LexState::default();
+        let mut line_start = 0;
+        for line in contents.split_inclusive('\n') {
+            let mut lexemes = StrTokenizer::new(line).with_state(state);
+            for lexeme in lexemes.by_ref() {
+                let offset = line_start + lexeme.unwrap().span.start.offset;
+                let i = tokens.partition_point(|i| i.span.end.offset <= offset);
+                assert_eq!(lexeme.unwrap().token, tokens[i].token);
+            }
+            state = lexemes.state();
+            line_start += line.len();
+        }
         println!("Tokenized into: {:#?}", tokens);
     }
 }
@@ -1,3 +1,6 @@
+use keyword::Keyword;
+use symbol::Symbol;
+
 /// Token which is usually paired with another token, i.e. is either left or right
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum PairedToken {
@@ -9,6 +12,26 @@ pub enum PairedToken {
     Brace,
 }
 
+impl PairedToken {
+    /// Left (opening) character of the pair
+    pub fn open(self) -> char {
+        match self {
+            PairedToken::Parenthesis => '(',
+            PairedToken::Bracket => '[',
+            PairedToken::Brace => '{',
+        }
+    }
+
+    /// Right (closing) character of the pair
+    pub fn close(self) -> char {
+        match self {
+            PairedToken::Parenthesis => ')',
+            PairedToken::Bracket => ']',
+            PairedToken::Brace => '}',
+        }
+    }
+}
+
 /// Operator on two expressions returning an expression of the same value
 ///
 /// Could be used with an assignment symbol when mutating a variable.
@@ -36,6 +59,158 @@ pub enum BinaryOperator {
     Shr,
 }
 
+/// Base of an integer literal
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Base {
+    /// `0b` prefix
+    Binary,
+    /// `0o` prefix
+    Octal,
+    /// No prefix
+    Decimal,
+    /// `0x` prefix
+    Hexadecimal,
+}
+
+impl Base {
+    /// Number of distinct digits in this base
+    pub fn radix(self) -> u32 {
+        match self {
+            Base::Binary => 2,
+            Base::Octal => 8,
+            Base::Decimal => 10,
+            Base::Hexadecimal => 16,
+        }
+    }
+}
+
+/// Type suffix of an integer literal, e.g. `u8` in `255u8`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum IntSuffix {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    Usize,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    Isize,
+}
+
+impl IntSuffix {
+    /// Find the suffix by the name of its type
+    pub fn from_name(name: &str) -> Option<Self> {
+        use self::IntSuffix::*;
+
+        Some(match name {
+            "u8" => U8,
+            "u16" => U16,
+            "u32" => U32,
+            "u64" => U64,
+            "u128" => U128,
+            "usize" => Usize,
+            "i8" => I8,
+            "i16" => I16,
+            "i32" => I32,
+            "i64" => I64,
+            "i128" => I128,
+            "isize" => Isize,
+            _ => return None,
+        })
+    }
+
+    /// Name of the type, as written in the suffix
+    pub fn as_str(self) -> &'static str {
+        use self::IntSuffix::*;
+
+        match self {
+            U8 => "u8",
+            U16 => "u16",
+            U32 => "u32",
+            U64 => "u64",
+            U128 => "u128",
+            Usize => "usize",
+            I8 => "i8",
+            I16 => "i16",
+            I32 => "i32",
+            I64 => "i64",
+            I128 => "i128",
+            Isize => "isize",
+        }
+    }
+}
+
+/// Type suffix of a float literal, e.g. `f32` in `1.5f32`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FloatSuffix {
+    F32,
+    F64,
+}
+
+impl FloatSuffix {
+    /// Find the suffix by the name of its type
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "f32" => Some(FloatSuffix::F32),
+            "f64" => Some(FloatSuffix::F64),
+            _ => None,
+        }
+    }
+
+    /// Name of the type, as written in the suffix
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FloatSuffix::F32 => "f32",
+            FloatSuffix::F64 => "f64",
+        }
+    }
+}
+
+/// Kind of a string literal, which defines the allowed characters and escapes
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum StrKind {
+    /// `"..."`, UTF-8 string
+    Str,
+    /// `b"..."`, ASCII string with arbitrary bytes allowed in escapes
+    Byte,
+    /// `c"..."`, nul-terminated UTF-8 string
+    C,
+}
+
+/// Whether a doc comment documents the item after it or the item it is inside of
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum DocStyle {
+    /// `///` or `/** */`, documents the following item
+    Outer,
+    /// `//!` or `/*! */`, documents the enclosing item
+    Inner,
+}
+
+/// Form of a comment
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum CommentKind {
+    /// `// ...`, lasts until the end of the line
+    Line,
+    /// `/* ... */`
+    Block,
+}
+
+/// Whether a punctuation token is immediately followed by another one, like `proc_macro::Spacing`
+///
+/// E.g. in `a >> b` the `>>` is `Alone`, while in `x>>-y` it is `Joint`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Spacing {
+    /// Followed by a punctuation character, with which it could form a different token
+    Joint,
+    /// Followed by whitespace, a delimiter, an identifier, a literal or the end of the source.
+    /// Tokens which are not punctuation are always alone
+    Alone,
+}
+
 /// Token - a lexical unit of the program source code
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Token {
@@ -46,8 +221,13 @@ pub enum Token {
     Right(PairedToken),
     /// Delimiting whitespace
     Whitespace,
-    /// Comments (including docs)
+    /// Comment which is not a doc comment, e.g. `// text` or `/* text */`
     Comment,
+    /// Doc comment, e.g. `/// text` or `/*! text */`
+    DocComment { style: DocStyle, kind: CommentKind },
+    /// First line of the source starting with `#!`, e.g. `#!/usr/bin/env run-cargo-script`,
+    /// unless it is an inner attribute `#![...]`
+    Shebang,
     /// `=`
     Equal,
 
@@ -60,12 +240,16 @@ pub enum Token {
     DoubleColon,
     /// `;`
     Semicolon,
-    /// `!`
+    /// `!`, used for negation, macro invocations and inner attributes
     Exclamation,
     /// `?`
     Question,
     /// `$`
     Dollar,
+    /// `@`, used in pattern bindings
+    At,
+    /// `_`, wildcard pattern and inferred type
+    Underscore,
     /// `'`, used for lifetimes, not chars.
     /// This token should never occur, lifetimes use `IdentifierLifetime`
     Quote,
@@ -94,10 +278,7 @@ pub enum Token {
     BinaryOperator(BinaryOperator),
     /// Binary operator with assignment, e.g. `+=`
     BinaryOperatorAssignment(BinaryOperator),
-    // Unary operators
-    /// `!`
-    Not,
-    /// `~`
+    /// `~`, not used in the language, but still a token
     Tilde,
     // Boolean and comparison operators
     /// `==`
@@ -118,304 +299,264 @@ pub enum Token {
     DoubleOr,
 
     // Literals
-    /// Integer and float literal
-    LiteralInt,
-    /// String literal
-    LiteralStr,
+    /// Integer literal, e.g. `0xFF` or `1_000u64`
+    LiteralInt {
+        base: Base,
+        suffix: Option<IntSuffix>,
+    },
+    /// Float literal, e.g. `1.5`, `1e10` or `2f32`
+    LiteralFloat { suffix: Option<FloatSuffix> },
+    /// String literal, e.g. `"str"`, `b"bytes"` or `r#"raw"#`
+    LiteralStr {
+        kind: StrKind,
+        /// Number of `#` symbols around a raw string, `None` for strings with escapes
+        raw: Option<u8>,
+    },
     /// Character literal
     LiteralChar,
+    /// Byte literal, e.g. `b'x'`
+    LiteralByte,
 
-    /// Function, variable or struct name or a keyword
+    /// Function, variable or struct name, including raw identifiers, e.g. `r#match`,
+    /// and keywords which are not reserved in the edition, e.g. `union`
     Identifier,
+    /// Keyword which can't be used as an identifier in the edition, e.g. `fn`
+    Keyword(Keyword),
     /// Lifetime identifier, including `'_` and `'static`
     IdentifierLifetime,
+
+    /// Character which can't start any token, only produced in the lossless mode
+    Unknown,
 }
 
-/// Try to convert a char into a binary operator
-/// This function does not output binary operators which are handled by special cases.
-fn char_to_binop(c: char) -> Option<BinaryOperator> {
-    match c {
-        '+' => Some(BinaryOperator::Plus),
-        // '-' => Some(BinaryOperator::Minus), // Handled as a special case due to arrows
-        '*' => Some(BinaryOperator::Star),
-        // '/' => Some(BinaryOperator::Slash), // Handled as a special case due to comments
-        '%' => Some(BinaryOperator::Percent),
-        '^' => Some(BinaryOperator::Caret),
-        '&' => Some(BinaryOperator::And),
-        '|' => Some(BinaryOperator::Or),
-        _ => None,
+impl Token {
+    /// Whether this is an operator or another punctuation token, except for delimiters and `_`
+    pub fn is_punctuation(self) -> bool {
+        use self::Token::*;
+
+        !matches!(
+            self,
+            Left(_)
+                | Right(_)
+                | Whitespace
+                | Comment
+                | DocComment { .. }
+                | Shebang
+                | Underscore
+                | LiteralInt { .. }
+                | LiteralFloat { .. }
+                | LiteralStr { .. }
+                | LiteralChar
+                | LiteralByte
+                | Identifier
+                | Keyword(_)
+                | IdentifierLifetime
+                | Unknown
+        )
     }
-}
 
-/// This character is eligible to be identifier's first char
-/// https://github.com/rust-lang/rust/blob/af50e3822c4ceda60445c4a2adbb3bfa480ebd39/src/libsyntax/parse/lexer/mod.rs#L1809
-fn is_ident_start(c: char) -> bool {
-    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || c == '_'
-    // || (c > '\x7f' && c.is_xid_start())
-}
+    /// Whether the token does not affect the meaning of the program, i.e. it is whitespace,
+    /// a comment which is not a doc comment or the shebang
+    pub fn is_trivia(self) -> bool {
+        matches!(self, Token::Whitespace | Token::Comment | Token::Shebang)
+    }
+
+    /// Split a compound punctuation token after its first character, e.g. `>>` into `>` and `>`
+    ///
+    /// Parsers need this when the lexer glued tokens that belong to different constructs,
+    /// e.g. the closing brackets of generics in `Vec<Vec<u8>>`.
+    /// Returns `None` for tokens of a single character and tokens which are not punctuation.
+    pub fn split(self) -> Option<(Token, Token)> {
+        use self::BinaryOperator::*;
+        use self::Token::*;
 
-/// This character is eligible to be identifier's non-first char
-fn is_ident_char(c: char) -> bool {
-    (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') || (c >= '0' && c <= '9') || c == '_'
-    // || (c > '\x7f' && c.is_xid_continue())
+        Some(match self {
+            DoubleColon => (Colon, Colon),
+            LeftArrow => (LessThan, BinaryOperator(Minus)),
+            RightArrow => (BinaryOperator(Minus), GreaterThan),
+            FatArrow => (Equal, GreaterThan),
+            DotDot => (Dot, Dot),
+            DotDotDot => (Dot, DotDot),
+            DotEq => (Dot, Equal),
+            DotDotEq => (Dot, DotEq),
+            BinaryOperator(Shl) => (LessThan, LessThan),
+            BinaryOperator(Shr) => (GreaterThan, GreaterThan),
+            BinaryOperatorAssignment(Shl) => (LessThan, LessEqual),
+            BinaryOperatorAssignment(Shr) => (GreaterThan, GreaterEqual),
+            BinaryOperatorAssignment(op) => (BinaryOperator(op), Equal),
+            DoubleEqual => (Equal, Equal),
+            LessEqual => (LessThan, Equal),
+            GreaterEqual => (GreaterThan, Equal),
+            NotEqual => (Exclamation, Equal),
+            DoubleAnd => (BinaryOperator(And), BinaryOperator(And)),
+            DoubleOr => (BinaryOperator(Or), BinaryOperator(Or)),
+            _ => return None,
+        })
+    }
 }
 
-/// Stream of tokens build from the iterator of characters
-pub struct Tokenizer<S> {
-    iter: S,
-    pos: usize,
-    cur: Option<char>,
+/// Location of a character in the source code
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Position {
+    /// Offset in bytes from the beginning of the source
+    pub offset: usize,
+    /// Line number, starting from 1
+    pub line: usize,
+    /// Column number in characters, starting from 1
+    pub column: usize,
 }
 
-impl<S> Tokenizer<S>
-where
-    S: Iterator<Item = char>,
-{
-    pub fn new(mut iter: S) -> Self {
-        let cur = iter.next();
-        Self { iter, pos: 0, cur }
+impl Position {
+    /// Position of the first character of the source
+    pub fn start() -> Self {
+        Position {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
     }
 
-    fn adv(&mut self) {
-        self.cur = self.iter.next();
-        self.pos += 1;
+    /// Position right after the given character
+    pub(crate) fn advance(&mut self, c: char) {
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
     }
 
-    /// Skip all chars for which `predicate` is true
-    fn skip_chars<F>(&mut self, mut predicate: F)
-    where
-        F: FnMut(char) -> bool,
-    {
-        while let Some(c) = self.cur {
-            if !predicate(c) {
-                break;
+    /// Position right after the given text
+    pub(crate) fn advance_str(&mut self, text: &str) {
+        self.offset += text.len();
+        match text.bytes().rposition(|i| i == b'\n') {
+            Some(last) => {
+                self.line += text.bytes().filter(|&i| i == b'\n').count();
+                self.column = text[last + 1..].chars().count() + 1;
             }
-            self.adv();
+            None => self.column += text.chars().count(),
         }
     }
+}
 
-    /// Skip all whitespace characters
-    fn skip_whitespace(&mut self) {
-        self.skip_chars(|i| i.is_ascii_whitespace());
-    }
+/// Region of the source code, `start` is inclusive and `end` is exclusive
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Span {
+    pub start: Position,
+    pub end: Position,
+}
 
-    /// Advance and return the next character
-    fn next(&mut self) -> Option<char> {
-        self.adv();
-        self.cur
+impl Span {
+    /// Length of the region in bytes
+    pub fn len(&self) -> usize {
+        self.end.offset - self.start.offset
     }
 
-    /// Tries to read a char from the stream as it would be in literals
-    ///
-    /// # Panics
-    /// When the input character is incorrect
-    fn read_char(&mut self, delimiter: char) {
-        match self.cur.unwrap() {
-            c if c == '\t' || c == '\r' || c == '\n' || c == '\'' => assert_ne!(delimiter, '\''),
-            '\r' => assert_eq!(self.next(), Some('\n')),
-            '\\' => match self.next().unwrap() {
-                'n' | 'r' | 't' | '\\' | '\'' | '"' | '0' => {}
-                'u' => {
-                    assert_eq!(self.next(), Some('{'));
-                    self.adv();
-                    self.skip_chars(|c| c.is_ascii_hexdigit());
-                    assert_eq!(self.cur, Some('}'));
-                }
-                'x' => {
-                    assert!(self.next().unwrap().is_ascii_hexdigit());
-                    assert!(self.next().unwrap().is_ascii_hexdigit());
-                }
-                '\n' if delimiter == '"' => self.skip_whitespace(),
-                c => panic!("Unexpected character {}", c),
-            },
-            _ => {}
-        };
-        self.adv();
+    /// Whether the region does not contain any characters
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
     }
 }
 
-impl<S> Iterator for Tokenizer<S>
-where
-    S: Iterator<Item = char>,
-{
-    type Item = Token;
+/// Token together with the region of the source it was read from
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+    /// Exact text of the token in the source, e.g. the name of an identifier
+    pub text: Symbol,
+    /// Whether the token is joint with the following punctuation token
+    pub spacing: Spacing,
+}
 
-    /// Retrieve the next token of incoming source code
+impl SpannedToken {
+    /// Split a compound punctuation token after its first character, see [`Token::split`]
     ///
-    /// # Panics
+    /// The first part is always joint with the second one, which keeps the spacing of the token.
+    pub fn split(&self) -> Option<(SpannedToken, SpannedToken)> {
+        let (first, second) = self.token.split()?;
+        // All punctuation is ASCII, so the first character is a single byte
+        let text = self.text.as_str();
+        let mut middle = self.span.start;
+        middle.advance(text.as_bytes()[0] as char);
+        Some((
+            SpannedToken {
+                token: first,
+                span: Span {
+                    start: self.span.start,
+                    end: middle,
+                },
+                text: Symbol::intern(&text[..1]),
+                spacing: Spacing::Joint,
+            },
+            SpannedToken {
+                token: second,
+                span: Span {
+                    start: middle,
+                    end: self.span.end,
+                },
+                text: Symbol::intern(&text[1..]),
+                spacing: self.spacing,
+            },
+        ))
+    }
+
+    /// Text of a doc comment without the comment markers, `None` for other tokens
     ///
-    /// When the tokenizer encounters an unexpected character
-    fn next(&mut self) -> Option<Token> {
-        use self::BinaryOperator::*;
-        use self::PairedToken::*;
-        use self::Token::*;
+    /// E.g. the doc text of `/// Docs` is ` Docs`.
+    pub fn doc(&self) -> Option<&'static str> {
+        doc_text(self.token, self.text.as_str())
+    }
+}
 
-        macro_rules! consume {
-            ($token: expr) => {{
-                self.adv();
-                $token
-            }};
-        }
+/// Token together with its text borrowed from the source, see `StrTokenizer`
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Lexeme<'a> {
+    pub token: Token,
+    pub span: Span,
+    /// Exact text of the token in the source
+    pub text: &'a str,
+    /// Whether the token is joint with the following punctuation token
+    pub spacing: Spacing,
+}
 
-        let cur = match self.cur {
-            Some(c) => c,
-            None => return None,
-        };
-
-        // === Binary operators ===
-        if let Some(binop) = char_to_binop(cur) {
-            return Some(match self.next() {
-                Some('=') => consume!(BinaryOperatorAssignment(binop)),
-                _ => BinaryOperator(binop),
-            });
-        }
-        // === Numerical literals ===
-        // TODO floats; _; types - u8, f64, etc
-        if cur.is_ascii_digit() {
-            self.skip_chars(|i| i.is_ascii_digit());
-            return Some(LiteralInt);
-        }
-        // === Identifiers ===
-        if is_ident_start(cur) {
-            self.skip_chars(is_ident_char);
-            return Some(Identifier);
+impl<'a> Lexeme<'a> {
+    /// Text of a doc comment without the comment markers, see [`SpannedToken::doc`]
+    pub fn doc(&self) -> Option<&'a str> {
+        doc_text(self.token, self.text)
+    }
+
+    /// Token with the interned text, as produced by the `Tokenizer`
+    pub fn to_spanned(&self) -> SpannedToken {
+        SpannedToken {
+            token: self.token,
+            span: self.span,
+            text: Symbol::intern(self.text),
+            spacing: self.spacing,
         }
+    }
+}
 
-        Some(match cur {
-            // === Special case for operators ===
-            // --- Needed to handle comments
-            '/' => {
-                match self.next() {
-                    Some('=') => consume!(BinaryOperatorAssignment(Slash)),
-                    // Block comments
-                    Some('*') => {
-                        while let Some(_) = self.next() {
-                            self.skip_chars(|i| i != '*');
-                            if let Some('/') = self.next() {
-                                self.adv();
-                                break;
-                            }
-                        }
-                        Comment
-                    }
-                    // Line comments
-                    Some('/') => {
-                        self.skip_chars(|i| i != '\n');
-                        Comment
-                    }
-                    _ => BinaryOperator(Slash),
-                }
-            }
-            // --- Needed to handle right arrows
-            '-' => match self.next() {
-                Some('=') => consume!(BinaryOperatorAssignment(Minus)),
-                Some('>') => consume!(RightArrow),
-                _ => BinaryOperator(Minus),
-            },
-            // === Structurals ===
-            ',' => consume!(Comma),
-            ';' => consume!(Semicolon),
-            '?' => consume!(Question),
-            '$' => consume!(Dollar),
-            '#' => consume!(Sharp),
-            '!' => match self.next() {
-                Some('=') => consume!(NotEqual),
-                _ => Exclamation,
-            },
-            ':' => match self.next() {
-                Some(':') => consume!(DoubleColon),
-                _ => Colon,
-            },
-            '.' => {
-                match self.next() {
-                    Some('.') => match self.next() {
-                        Some('.') => consume!(DotDotDot),
-                        Some('=') => consume!(DotDotEq),
-                        _ => DotDot,
-                    },
-                    //Some('=') => consume!(DotEq), // This token should never occur in real code
-                    _ => Dot,
-                }
-            }
-            // === Lifetimes and character literals ===
-            '\'' => {
-                match self.next() {
-                    // At this point we check whether the first symbol could be the start of lifetime
-                    Some(c) if is_ident_start(c) => {
-                        match self.next() {
-                            // If it is and the next symbol is a single quote, then it is a char literal
-                            Some('\'') => consume!(LiteralChar),
-                            // If it's not, then it is a lifetime identifier
-                            Some(c2) if is_ident_char(c2) => {
-                                self.skip_chars(is_ident_char);
-                                // Lifetimes can't have a closing quote at the end
-                                // The user could mistakenly try to create a char literal with multiple codepoints
-                                assert_ne!(
-                                    self.cur,
-                                    Some('\''),
-                                    "Char literal must have at most one codepoint"
-                                );
-                                IdentifierLifetime
-                            }
-                            _ => IdentifierLifetime,
-                        }
-                    }
-                    Some('\'') => panic!("You can't simply put two single quotes in a row"),
-                    // The character is not the start of a lifetime identifier, it is a char literal
-                    Some(_) => {
-                        self.read_char('\'');
-                        assert_eq!(self.cur, Some('\''), "Expected single quote");
-                        self.next();
-                        LiteralChar
-                    }
-                    None => panic!("EOF after opening quote"),
-                }
-            }
-            // === Paired tokens ===
-            '(' => consume!(Left(Parenthesis)),
-            ')' => consume!(Right(Parenthesis)),
-            '{' => consume!(Left(Brace)),
-            '}' => consume!(Right(Brace)),
-            '[' => consume!(Left(Bracket)),
-            ']' => consume!(Right(Bracket)),
-            // === String literals ===
-            // TODO other types of literals
-            '\"' => {
-                self.adv();
-                while self.cur != Some('"') {
-                    self.read_char('"');
-                }
-                self.adv();
-                LiteralStr
-            }
-            // === Comparison operators and assignment ===
-            '<' => match self.next() {
-                Some('=') => consume!(LessEqual),
-                Some('-') => consume!(LeftArrow),
-                Some('<') => match self.next() {
-                    Some('=') => consume!(BinaryOperatorAssignment(Shl)),
-                    _ => BinaryOperator(Shl),
-                },
-                _ => LessThan,
-            },
-            '>' => match self.next() {
-                Some('=') => consume!(GreaterEqual),
-                Some('>') => match self.next() {
-                    Some('=') => consume!(BinaryOperatorAssignment(Shr)),
-                    _ => BinaryOperator(Shr),
-                },
-                _ => GreaterThan,
-            },
-            '=' => match self.next() {
-                Some('=') => consume!(DoubleEqual),
-                Some('>') => consume!(RightArrow),
-                _ => Equal,
-            },
-            _ if cur.is_ascii_whitespace() => {
-                self.skip_whitespace();
-                Whitespace
-            }
-            _ => panic!("Unexpected character {} at location {}", cur, self.pos),
-        })
+/// Strip the markers from the text of a doc comment
+fn doc_text(token: Token, text: &str) -> Option<&str> {
+    let kind = match token {
+        Token::DocComment { kind, .. } => kind,
+        _ => return None,
+    };
+    // Lines continuing a block comment lexed with a `LexState` don't have the opening marker
+    let text = match kind {
+        CommentKind::Line => text
+            .strip_prefix("///")
+            .or_else(|| text.strip_prefix("//!")),
+        CommentKind::Block => text
+            .strip_prefix("/**")
+            .or_else(|| text.strip_prefix("/*!")),
     }
+    .unwrap_or(text);
+    Some(match kind {
+        CommentKind::Line => text.strip_suffix('\r').unwrap_or(text),
+        // Unterminated comments and their lines but the last don't have the closing marker
+        CommentKind::Block => text.strip_suffix("*/").unwrap_or(text),
+    })
 }
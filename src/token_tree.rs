@@ -0,0 +1,240 @@
+//! Grouping of the token stream into trees of matched delimiters
+//!
+//! Similar to `proc_macro::TokenTree`, whitespace, comments (but not doc comments) and the
+//! shebang are dropped, and tokens between paired delimiters become groups.
+
+use std::error::Error;
+use std::fmt;
+
+use token::{PairedToken, Span, SpannedToken, Token};
+use tokenizer::LexError;
+
+/// Single token or a delimited group of token trees
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum TokenTree {
+    /// Any token except for delimiters, whitespace, comments and the shebang
+    Token(SpannedToken),
+    /// Token trees between a pair of delimiters
+    Group(Group),
+}
+
+impl TokenTree {
+    /// Region of the source covered by the tree
+    pub fn span(&self) -> Span {
+        match *self {
+            TokenTree::Token(ref token) => token.span,
+            TokenTree::Group(ref group) => group.span(),
+        }
+    }
+}
+
+/// Token trees enclosed in delimiters, e.g. `(a, b)`
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Group {
+    pub delimiter: PairedToken,
+    /// Span of the opening delimiter
+    pub open: Span,
+    /// Span of the closing delimiter, `None` if the group is not closed
+    pub close: Option<Span>,
+    pub stream: Vec<TokenTree>,
+}
+
+impl Group {
+    /// Region of the source from the opening delimiter to the closing one, or to the end of the
+    /// last tree inside of the group if it is not closed
+    pub fn span(&self) -> Span {
+        let end = match self.close {
+            Some(close) => close.end,
+            None => self.stream.last().map_or(self.open.end, |i| i.span().end),
+        };
+        Span {
+            start: self.open.start,
+            end,
+        }
+    }
+}
+
+/// Error found while building token trees
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TreeError {
+    /// Error of the tokenizer
+    Lex(LexError),
+    /// Closing delimiter does not match the innermost opening one, e.g. `(]`
+    Mismatched {
+        open_delimiter: PairedToken,
+        open: Span,
+        close_delimiter: PairedToken,
+        close: Span,
+    },
+    /// Opening delimiter without a closing one before the end of the input
+    Unclosed {
+        delimiter: PairedToken,
+        open: Span,
+        /// Empty span at the end of the input
+        end: Span,
+    },
+    /// Closing delimiter which does not match any open group, unless it is the first one reported
+    /// as `Mismatched` in the innermost group
+    Unexpected { delimiter: PairedToken, close: Span },
+}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TreeError::Lex(ref error) => error.fmt(f),
+            TreeError::Mismatched {
+                open_delimiter,
+                open,
+                close_delimiter,
+                close,
+            } => write!(
+                f,
+                "mismatched closing delimiter `{}` at {}:{} for `{}` at {}:{}",
+                close_delimiter.close(),
+                close.start.line,
+                close.start.column,
+                open_delimiter.open(),
+                open.start.line,
+                open.start.column
+            ),
+            TreeError::Unclosed {
+                delimiter,
+                open,
+                end,
+            } => write!(
+                f,
+                "unclosed delimiter `{}` at {}:{}, the input ends at {}:{}",
+                delimiter.open(),
+                open.start.line,
+                open.start.column,
+                end.start.line,
+                end.start.column
+            ),
+            TreeError::Unexpected { delimiter, close } => write!(
+                f,
+                "unexpected closing delimiter `{}` at {}:{}",
+                delimiter.close(),
+                close.start.line,
+                close.start.column
+            ),
+        }
+    }
+}
+
+impl Error for TreeError {}
+
+/// Group which is being built
+struct Frame {
+    delimiter: PairedToken,
+    open: Span,
+    stream: Vec<TokenTree>,
+    /// Whether a mismatched closing delimiter was already reported for this group
+    mismatched: bool,
+}
+
+impl Frame {
+    fn into_group(self, close: Option<Span>) -> TokenTree {
+        TokenTree::Group(Group {
+            delimiter: self.delimiter,
+            open: self.open,
+            close,
+            stream: self.stream,
+        })
+    }
+}
+
+/// Build token trees from the stream of tokens, e.g. `Tokenizer::spanned`
+///
+/// Errors do not stop the building. A closing delimiter matching one of the outer groups closes
+/// the inner groups as unclosed, other unmatched closing delimiters are reported and skipped.
+/// Groups left open at the end of the input are unclosed too.
+pub fn build<I>(tokens: I) -> (Vec<TokenTree>, Vec<TreeError>)
+where
+    I: IntoIterator<Item = Result<SpannedToken, LexError>>,
+{
+    let mut root = Vec::new();
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut errors = Vec::new();
+    let mut end = None;
+
+    for token in tokens {
+        let token = match token {
+            Ok(token) => token,
+            Err(error) => {
+                errors.push(TreeError::Lex(error));
+                continue;
+            }
+        };
+        end = Some(token.span.end);
+        match token.token {
+            token if token.is_trivia() => {}
+            Token::Left(delimiter) => frames.push(Frame {
+                delimiter,
+                open: token.span,
+                stream: Vec::new(),
+                mismatched: false,
+            }),
+            Token::Right(delimiter) => {
+                let matching = frames.iter().rposition(|i| i.delimiter == delimiter);
+                match frames.last_mut() {
+                    None => errors.push(TreeError::Unexpected {
+                        delimiter,
+                        close: token.span,
+                    }),
+                    Some(ref innermost) if innermost.delimiter == delimiter => {}
+                    // Each group is reported as mismatched only once, further closing delimiters
+                    // which don't close any group are still reported
+                    Some(ref innermost) if innermost.mismatched => {
+                        if matching.is_none() {
+                            errors.push(TreeError::Unexpected {
+                                delimiter,
+                                close: token.span,
+                            });
+                        }
+                    }
+                    Some(innermost) => {
+                        innermost.mismatched = true;
+                        errors.push(TreeError::Mismatched {
+                            open_delimiter: innermost.delimiter,
+                            open: innermost.open,
+                            close_delimiter: delimiter,
+                            close: token.span,
+                        });
+                    }
+                }
+                if let Some(matching) = matching {
+                    // Groups inside of the matching one are not closed
+                    while frames.len() > matching + 1 {
+                        let frame = frames.pop().unwrap();
+                        let group = frame.into_group(None);
+                        frames.last_mut().unwrap().stream.push(group);
+                    }
+                    let group = frames.pop().unwrap().into_group(Some(token.span));
+                    match frames.last_mut() {
+                        Some(parent) => parent.stream.push(group),
+                        None => root.push(group),
+                    }
+                }
+            }
+            _ => match frames.last_mut() {
+                Some(frame) => frame.stream.push(TokenTree::Token(token)),
+                None => root.push(TokenTree::Token(token)),
+            },
+        }
+    }
+
+    let end = end.map(|end| Span { start: end, end });
+    while let Some(frame) = frames.pop() {
+        errors.push(TreeError::Unclosed {
+            delimiter: frame.delimiter,
+            open: frame.open,
+            end: end.unwrap_or(frame.open),
+        });
+        let group = frame.into_group(None);
+        match frames.last_mut() {
+            Some(parent) => parent.stream.push(group),
+            None => root.push(group),
+        }
+    }
+    (root, errors)
+}
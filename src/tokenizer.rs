@@ -0,0 +1,1617 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::BufRead;
+use std::str::{self, Utf8Error};
+
+use edition::Edition;
+use keyword::Keyword;
+use reader::ReadChars;
+use symbol::Symbol;
+use token::*;
+use unicode;
+
+/// Try to convert a char into a binary operator
+/// This function does not output binary operators which are handled by special cases.
+fn char_to_binop(c: char) -> Option<BinaryOperator> {
+    match c {
+        '+' => Some(BinaryOperator::Plus),
+        // '-' => Some(BinaryOperator::Minus), // Handled as a special case due to arrows
+        '*' => Some(BinaryOperator::Star),
+        // '/' => Some(BinaryOperator::Slash), // Handled as a special case due to comments
+        '%' => Some(BinaryOperator::Percent),
+        '^' => Some(BinaryOperator::Caret),
+        '&' => Some(BinaryOperator::And),
+        '|' => Some(BinaryOperator::Or),
+        _ => None,
+    }
+}
+
+/// Build a comment token, which is a doc comment if it has a style
+fn comment(style: Option<DocStyle>, kind: CommentKind) -> Token {
+    match style {
+        Some(style) => Token::DocComment { style, kind },
+        None => Token::Comment,
+    }
+}
+
+/// This character is eligible to be identifier's first char
+/// <https://github.com/rust-lang/rust/blob/af50e3822c4ceda60445c4a2adbb3bfa480ebd39/src/libsyntax/parse/lexer/mod.rs#L1809>
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || (c > '\x7f' && unicode::is_xid_start(c))
+}
+
+/// This character is eligible to be identifier's non-first char
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || (c > '\x7f' && unicode::is_xid_continue(c))
+}
+
+/// First byte of the UTF-8 encoding of the character
+fn first_byte(c: char) -> u8 {
+    let mut bytes = [0; 4];
+    c.encode_utf8(&mut bytes);
+    bytes[0]
+}
+
+/// Whether the byte is a character which stands for itself in literals of the given kind, except
+/// for the quote and the backslash
+fn plain_byte(byte: u8, kind: StrKind) -> bool {
+    match byte {
+        b'\r' => false,
+        0 => kind != StrKind::C,
+        _ => byte.is_ascii() || kind != StrKind::Byte,
+    }
+}
+
+/// Kind of an error which could occur while reading a token
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LexErrorKind {
+    /// Character which can't start any token
+    UnexpectedChar(char),
+    /// Escape sequence with an unknown character after the backslash, e.g. `\q`
+    UnknownEscape(char),
+    /// Malformed or out of range `\x` or `\u` escape sequence
+    InvalidEscape,
+    /// Character which must be escaped in this literal, e.g. a tab in a char literal
+    UnescapedChar(char),
+    /// `''`
+    EmptyChar,
+    /// Char literal with several codepoints inside, e.g. `'ab'`
+    MultiCodepointChar,
+    /// Char literal without the closing quote
+    UnterminatedChar,
+    /// String literal without the closing quote
+    UnterminatedStr,
+    /// Block comment without the closing `*/`
+    UnterminatedBlockComment {
+        /// Start of the innermost unclosed comment nested into it, if any
+        last_nested: Option<Position>,
+    },
+    /// Digit which is not allowed in the base of an integer literal, e.g. `2` in `0b102`
+    InvalidDigit(char),
+    /// Integer literal with a base prefix, but without any digits, e.g. `0x`
+    NoDigits,
+    /// Exponent of a float literal without any digits, e.g. `1e+`
+    EmptyExponent,
+    /// Suffix of a number literal which is not a numeric type, e.g. `1u7`
+    InvalidSuffix,
+    /// Non-ASCII character in a byte or byte string literal
+    NonAsciiByte(char),
+    /// `\u` escape sequence in a byte or byte string literal
+    UnicodeEscapeInByte,
+    /// Nul character in a C string literal
+    NulInCStr,
+    /// Character other than `#` between the prefix and the quote of a raw string, e.g. `r#-"`
+    InvalidRawStrDelimiter(char),
+    /// Raw string delimited by more than 255 `#` symbols
+    TooManyHashes,
+    /// Name which can't be used as a raw identifier, e.g. `self` of `r#self`
+    InvalidRawIdentifier(&'static str),
+}
+
+impl fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use self::LexErrorKind::*;
+
+        match *self {
+            UnexpectedChar(c) => write!(f, "unknown start of token: {}", c.escape_debug()),
+            UnknownEscape(c) => write!(f, "unknown character escape: {}", c.escape_debug()),
+            InvalidEscape => write!(f, "invalid escape sequence"),
+            UnescapedChar(c) => write!(f, "character must be escaped: {}", c.escape_default()),
+            EmptyChar => write!(f, "empty character literal"),
+            MultiCodepointChar => write!(f, "character literal may only contain one codepoint"),
+            UnterminatedChar => write!(f, "unterminated character literal"),
+            UnterminatedStr => write!(f, "unterminated double quote string"),
+            UnterminatedBlockComment {
+                last_nested: Some(nested),
+            } => write!(
+                f,
+                "unterminated block comment (nested comment at {}:{} is not closed)",
+                nested.line, nested.column
+            ),
+            UnterminatedBlockComment { last_nested: None } => {
+                write!(f, "unterminated block comment")
+            }
+            InvalidDigit(c) => write!(f, "invalid digit for the base of the literal: {}", c),
+            NoDigits => write!(f, "no valid digits found for number"),
+            EmptyExponent => write!(f, "expected at least one digit in exponent"),
+            InvalidSuffix => write!(f, "invalid suffix for number literal"),
+            NonAsciiByte(c) => write!(f, "non-ASCII character in byte literal: {}", c),
+            UnicodeEscapeInByte => write!(f, "unicode escape in byte string"),
+            NulInCStr => write!(f, "null characters in C string literals are not supported"),
+            InvalidRawStrDelimiter(c) => write!(
+                f,
+                "only `#` is allowed in raw string delimitation, found {}",
+                c.escape_debug()
+            ),
+            TooManyHashes => write!(f, "raw strings may be delimited by up to 255 `#` symbols"),
+            InvalidRawIdentifier(name) => write!(f, "`{}` cannot be a raw identifier", name),
+        }
+    }
+}
+
+/// Error which occurred while reading a token, together with its location
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} at {}:{}",
+            self.kind, self.span.start.line, self.span.start.column
+        )
+    }
+}
+
+impl Error for LexError {}
+
+/// State of the lexer between two parts of a source, e.g. at the end of a line
+///
+/// Lexing a source line by line, each line starting in the state at the end of the previous
+/// one, gives the same tokens as lexing the whole source, except that:
+///
+/// - tokens spanning several lines are split at the ends of the lines,
+/// - `#!` on the first line is a shebang even if an inner attribute starts on the next lines,
+///   e.g. `#!\n[allow(unused)]`, as they are not seen yet,
+/// - a carriage return without a line feed in the indentation after a line continuation of a
+///   string is reported as an error, while it is skipped with the indentation in the whole source.
+///
+/// Char literals end at a line break, even a malformed one.
+///
+///
+/// ```
+/// use rust_lexer::token::StrKind;
+/// use rust_lexer::{LexState, StrTokenizer, Token};
+///
+/// let mut state = LexState::default();
+/// let mut lines = Vec::new();
+/// for line in "let s = r#\"\n\"#; /* a\nb */".split_inclusive('\n') {
+///     let mut tokens = StrTokenizer::new(line).with_state(state);
+///     lines.push(tokens.by_ref().map(|i| i.unwrap().token).collect::<Vec<_>>());
+///     state = tokens.state();
+/// }
+/// let raw = Token::LiteralStr { kind: StrKind::Str, raw: Some(1) };
+/// assert_eq!(lines[1][0], raw);
+/// assert_eq!(lines[2][0], Token::Comment);
+/// assert_eq!(state, LexState::Normal { after_dot: false });
+/// ```
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum LexState {
+    /// At the start of the source, where a shebang can be
+    Start,
+    /// Between tokens, `after_dot` is set if the last token was `.`
+    Normal { after_dot: bool },
+    /// Inside of a block comment with the given nesting depth, starting from 1
+    BlockComment {
+        depth: usize,
+        style: Option<DocStyle>,
+    },
+    /// Inside of a string literal, `raw` is the number of `#` of a raw string
+    Str { kind: StrKind, raw: Option<u8> },
+}
+
+/// The state at the start of a whole source
+impl Default for LexState {
+    fn default() -> Self {
+        LexState::Start
+    }
+}
+
+/// Source of characters for the lexer, which also provides the texts of tokens
+trait Source {
+    /// Consume and return the next character
+    fn next(&mut self) -> Option<char>;
+    /// Look at the character which will be returned by `next` without consuming it
+    fn peek(&mut self) -> Option<char>;
+    /// Pass the characters which will be returned by `next` to `f` without consuming them, until
+    /// it returns `false` or the source ends
+    fn scan_ahead<F: FnMut(char) -> bool>(&mut self, f: F);
+    /// Consume and keep the characters while `skip` is true for their first bytes, returning
+    /// their text
+    ///
+    /// `skip` must give the same result for all non-ASCII bytes, so that the characters can be
+    /// skipped by scanning the bytes of the source.
+    fn skip_bytes<F: Fn(u8) -> bool>(&mut self, skip: F) -> &str;
+    /// Consume and keep the characters up to the end of the line, returning their text
+    fn skip_line(&mut self) -> &str {
+        self.skip_bytes(|i| i != b'\n')
+    }
+    /// Remember the character passed by the lexer as a part of the current token
+    fn keep(&mut self, c: char);
+    /// Start a new token at the byte offset, forgetting the text of the previous one
+    fn start(&mut self, offset: usize);
+    /// Text of the current token between the byte offsets in the source
+    fn text(&self, start: usize, end: usize) -> &str;
+}
+
+/// Source reading from an iterator, the text of the current token is copied into a buffer
+struct CharSource<S> {
+    iter: S,
+    /// Characters looked at by `peek` and `scan_ahead` since the last `next`
+    lookahead: VecDeque<char>,
+    buf: String,
+    /// Offset of the beginning of `buf` in the source
+    start: usize,
+}
+
+impl<S> Source for CharSource<S>
+where
+    S: Iterator<Item = char>,
+{
+    fn next(&mut self) -> Option<char> {
+        self.lookahead.pop_front().or_else(|| self.iter.next())
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        if self.lookahead.is_empty() {
+            let c = self.iter.next()?;
+            self.lookahead.push_back(c);
+        }
+        self.lookahead.front().cloned()
+    }
+
+    fn scan_ahead<F: FnMut(char) -> bool>(&mut self, mut f: F) {
+        for &c in &self.lookahead {
+            if !f(c) {
+                return;
+            }
+        }
+        for c in self.iter.by_ref() {
+            self.lookahead.push_back(c);
+            if !f(c) {
+                return;
+            }
+        }
+    }
+
+    fn skip_bytes<F: Fn(u8) -> bool>(&mut self, skip: F) -> &str {
+        let len = self.buf.len();
+        while let Some(c) = self.peek() {
+            if !skip(first_byte(c)) {
+                break;
+            }
+            self.next();
+            self.buf.push(c);
+        }
+        &self.buf[len..]
+    }
+
+    fn keep(&mut self, c: char) {
+        self.buf.push(c);
+    }
+
+    fn start(&mut self, offset: usize) {
+        self.buf.clear();
+        self.start = offset;
+    }
+
+    fn text(&self, start: usize, end: usize) -> &str {
+        &self.buf[start - self.start..end - self.start]
+    }
+}
+
+/// Source scanning the bytes of a string, texts are slices of it
+struct StrSource<'a> {
+    src: &'a str,
+    /// Offset of the character which will be returned by `next`
+    offset: usize,
+}
+
+impl<'a> StrSource<'a> {
+    /// Decode the character at the byte offset, which is a char boundary
+    fn char_at(&self, offset: usize) -> Option<char> {
+        let byte = *self.src.as_bytes().get(offset)?;
+        if byte.is_ascii() {
+            Some(byte as char)
+        } else {
+            self.src[offset..].chars().next()
+        }
+    }
+}
+
+impl<'a> Source for StrSource<'a> {
+    fn next(&mut self) -> Option<char> {
+        let c = self.char_at(self.offset)?;
+        self.offset += c.len_utf8();
+        Some(c)
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.char_at(self.offset)
+    }
+
+    fn scan_ahead<F: FnMut(char) -> bool>(&mut self, mut f: F) {
+        for c in self.src[self.offset..].chars() {
+            if !f(c) {
+                return;
+            }
+        }
+    }
+
+    fn skip_bytes<F: Fn(u8) -> bool>(&mut self, skip: F) -> &str {
+        let start = self.offset;
+        let bytes = &self.src.as_bytes()[start..];
+        self.offset += bytes.iter().position(|&i| !skip(i)).unwrap_or(bytes.len());
+        &self.src[start..self.offset]
+    }
+
+    fn skip_line(&mut self) -> &str {
+        let rest = &self.src[self.offset..];
+        let line = &rest[..rest.find('\n').unwrap_or(rest.len())];
+        self.offset += line.len();
+        line
+    }
+
+    fn keep(&mut self, _: char) {}
+
+    fn start(&mut self, _: usize) {}
+
+    fn text(&self, start: usize, end: usize) -> &str {
+        &self.src[start..end]
+    }
+}
+
+/// Place in the whitespace and comments after `#!` scanned by `Lexer::is_inner_attribute`
+#[derive(Copy, Clone)]
+enum Scan {
+    /// Between comments
+    Between,
+    /// After `/`
+    Slash,
+    /// After `//`
+    LineStart,
+    /// After `///`
+    LineSlash,
+    /// Inside of a line comment which is not a doc comment
+    Line,
+    /// After `/*`
+    BlockStart,
+    /// After `/**`
+    BlockStar,
+    /// Inside of a block comment with the given nesting depth, after the `last` character which
+    /// could start `/*` or `*/`
+    Block { depth: usize, last: Option<char> },
+}
+
+impl Scan {
+    /// Place after the character inside of a block comment
+    fn block(depth: usize, last: Option<char>, c: char) -> Scan {
+        match (last, c) {
+            (Some('/'), '*') => Scan::Block {
+                depth: depth + 1,
+                last: None,
+            },
+            (Some('*'), '/') if depth == 1 => Scan::Between,
+            (Some('*'), '/') => Scan::Block {
+                depth: depth - 1,
+                last: None,
+            },
+            _ => Scan::Block {
+                depth,
+                last: Some(c),
+            },
+        }
+    }
+}
+
+/// Token read by the lexer, its text is available from the source until the next token is read
+struct Lexed {
+    token: Token,
+    span: Span,
+    spacing: Spacing,
+}
+
+/// State machine reading tokens, shared by the tokenizers of all sources
+struct Lexer<S> {
+    source: S,
+    pos: Position,
+    cur: Option<char>,
+    /// Tokens and errors which were already read, but not yet yielded
+    pending: VecDeque<Result<Lexed, LexError>>,
+    /// Whether the last token was `.`, so that a number after it is a tuple field
+    after_dot: bool,
+    edition: Edition,
+    /// Whether unexpected characters are kept as `Unknown` tokens
+    lossless: bool,
+    /// Whether the source is a part of a larger one, so that literals and comments left open
+    /// at its end continue in the next part instead of being unterminated
+    partial: bool,
+    /// Literal or comment the source starts inside of, until its first token is read, or which
+    /// is left open at the end of a partial source
+    open: Option<LexState>,
+    /// Whether the source is not a continuation of another one, so it can start with a shebang
+    whole: bool,
+}
+
+impl<S: Source> Lexer<S> {
+    fn new(mut source: S) -> Self {
+        let cur = source.next();
+        Lexer {
+            source,
+            pos: Position::start(),
+            cur,
+            pending: VecDeque::new(),
+            after_dot: false,
+            edition: Edition::default(),
+            lossless: false,
+            partial: false,
+            open: None,
+            whole: true,
+        }
+    }
+
+    /// Lex a part of a larger source, which starts in the given state
+    fn set_state(&mut self, state: LexState) {
+        self.partial = true;
+        self.whole = state == LexState::Start;
+        self.after_dot = state == LexState::Normal { after_dot: true };
+        self.open = match state {
+            LexState::BlockComment { .. } | LexState::Str { .. } => Some(state),
+            LexState::Start | LexState::Normal { .. } => None,
+        };
+    }
+
+    /// State after the tokens read so far
+    fn state(&self) -> LexState {
+        match self.open {
+            Some(state) => state,
+            None if self.whole && self.pos.offset == 0 => LexState::Start,
+            None => LexState::Normal {
+                after_dot: self.after_dot,
+            },
+        }
+    }
+
+    /// Read the next token or error
+    fn next_token(&mut self) -> Option<Result<Lexed, LexError>> {
+        if self.pending.is_empty() {
+            self.cur?;
+            let start = self.pos;
+            self.source.start(start.offset);
+            let token = match self.open.take() {
+                Some(state) => Some(self.read_continued(start, state)),
+                None => self.read_token(start),
+            };
+            self.after_dot = token == Some(Token::Dot);
+            if let Some(token) = token {
+                let spacing = if token.is_punctuation() && self.is_joint() {
+                    Spacing::Joint
+                } else {
+                    Spacing::Alone
+                };
+                let lexed = Lexed {
+                    token,
+                    span: Span {
+                        start,
+                        end: self.pos,
+                    },
+                    spacing,
+                };
+                // Most tokens have no errors, which would have to be yielded before them
+                if self.pending.is_empty() {
+                    return Some(Ok(lexed));
+                }
+                self.pending.push_back(Ok(lexed));
+            }
+        }
+        self.pending.pop_front()
+    }
+
+    /// Whether the current character starts a punctuation token, which makes the previous one joint
+    fn is_joint(&mut self) -> bool {
+        match self.cur {
+            // Comments are not punctuation
+            Some('/') => !matches!(self.peek(), Some('/') | Some('*')),
+            Some(c) => "=<>!~+-*%^&|@.,;:#$?".contains(c),
+            None => false,
+        }
+    }
+
+    fn adv(&mut self) {
+        if let Some(c) = self.cur {
+            self.pos.advance(c);
+            self.source.keep(c);
+        }
+        self.cur = self.source.next();
+    }
+
+    /// Report an error spanning from `start` to the current position
+    fn error(&mut self, kind: LexErrorKind, start: Position) {
+        self.pending.push_back(Err(LexError {
+            kind,
+            span: Span {
+                start,
+                end: self.pos,
+            },
+        }));
+    }
+
+    /// Skip all chars for which `predicate` is true
+    fn skip_chars<F>(&mut self, mut predicate: F)
+    where
+        F: FnMut(char) -> bool,
+    {
+        while let Some(c) = self.cur {
+            if !predicate(c) {
+                break;
+            }
+            self.adv();
+        }
+    }
+
+    /// Skip the characters while `skip` is true for their first bytes, see `Source::skip_bytes`
+    ///
+    /// Returns whether any characters were skipped.
+    fn skip_bytes<F: Fn(u8) -> bool>(&mut self, skip: F) -> bool {
+        match self.cur {
+            Some(c) if skip(first_byte(c)) => {
+                self.pos.advance(c);
+                self.source.keep(c);
+                // ASCII text without line breaks, e.g. of identifiers, only moves the column
+                let simple = !skip(b'\n') && !skip(0x80);
+                let text = self.source.skip_bytes(&skip);
+                if simple {
+                    self.pos.offset += text.len();
+                    self.pos.column += text.len();
+                } else {
+                    self.pos.advance_str(text);
+                }
+                self.cur = self.source.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Skip the characters up to the end of the line, but not the line break itself
+    fn skip_line(&mut self) {
+        if let Some(c) = self.cur.filter(|&c| c != '\n') {
+            self.pos.advance(c);
+            self.source.keep(c);
+            let text = self.source.skip_line();
+            self.pos.offset += text.len();
+            self.pos.column += text.chars().count();
+            self.cur = self.source.next();
+        }
+    }
+
+    /// Skip all whitespace characters
+    fn skip_whitespace(&mut self) {
+        self.skip_bytes(|i| i.is_ascii_whitespace());
+    }
+
+    /// Skip the characters of an identifier, ASCII ones are skipped in runs
+    fn skip_ident_chars(&mut self) {
+        self.skip_bytes(|i| i.is_ascii_alphanumeric() || i == b'_');
+        self.skip_chars(is_ident_char);
+    }
+
+    /// Advance and return the next character
+    fn next(&mut self) -> Option<char> {
+        self.adv();
+        self.cur
+    }
+
+    /// Look at the character after the current one without advancing
+    fn peek(&mut self) -> Option<char> {
+        self.source.peek()
+    }
+
+    /// Whether the `#!` before the next character is followed by `[`, which makes it the start
+    /// of an inner attribute rather than a shebang
+    ///
+    /// Like rustc, whitespace and comments which are not doc comments are skipped before `[`.
+    /// The characters are scanned once, a `Tokenizer` keeps them until they are lexed, which is
+    /// only a few characters unless whitespace or comments follow the `#!`.
+    fn is_inner_attribute(&mut self) -> bool {
+        let mut scan = Scan::Between;
+        let mut attribute = false;
+        self.source.scan_ahead(|c| {
+            scan = match scan {
+                Scan::Between if c == '[' => {
+                    attribute = true;
+                    return false;
+                }
+                Scan::Between if c.is_ascii_whitespace() => Scan::Between,
+                Scan::Between if c == '/' => Scan::Slash,
+                Scan::Slash if c == '/' => Scan::LineStart,
+                Scan::Slash if c == '*' => Scan::BlockStart,
+                // `//!` and `///` are doc comments, unlike `////`
+                Scan::LineStart if c == '!' => return false,
+                Scan::LineStart if c == '/' => Scan::LineSlash,
+                Scan::LineSlash if c != '/' => return false,
+                Scan::LineStart | Scan::LineSlash | Scan::Line if c == '\n' => Scan::Between,
+                Scan::LineStart | Scan::LineSlash | Scan::Line => Scan::Line,
+                // `/*!` and `/**` are doc comments, unlike `/**/` and `/***`
+                Scan::BlockStart if c == '!' => return false,
+                Scan::BlockStart if c == '*' => Scan::BlockStar,
+                Scan::BlockStar if c != '*' && c != '/' => return false,
+                Scan::BlockStart => Scan::block(1, None, c),
+                Scan::BlockStar => Scan::block(1, Some('*'), c),
+                Scan::Block { depth, last } => Scan::block(depth, last, c),
+                Scan::Between | Scan::Slash => return false,
+            };
+            true
+        });
+        attribute
+    }
+
+    /// Read digits of a number in the given base, which can be separated by underscores
+    ///
+    /// Decimal digits which don't belong to the base are reported and skipped.
+    /// Returns the number of digits read.
+    fn read_digits(&mut self, base: Base) -> usize {
+        let radix = base.radix();
+        let start = self.pos.offset;
+        self.skip_bytes(|i| i == b'_' || char::from(i).is_digit(radix));
+        let digits = self.source.text(start, self.pos.offset);
+        let mut count = digits.bytes().filter(|&i| i != b'_').count();
+        while let Some(c) = self.cur {
+            if c == '_' {
+                self.adv();
+                continue;
+            }
+            let valid = c.is_digit(base.radix());
+            if !valid && !c.is_ascii_digit() {
+                break;
+            }
+            let start = self.pos;
+            self.adv();
+            if valid {
+                count += 1;
+            } else {
+                self.error(LexErrorKind::InvalidDigit(c), start);
+            }
+        }
+        count
+    }
+
+    /// Read an integer or float literal starting with a decimal digit
+    fn read_number(&mut self, start: Position) -> Token {
+        use self::LexErrorKind::*;
+
+        let prefixed = match (self.cur, self.peek()) {
+            (Some('0'), Some('b')) => Some(Base::Binary),
+            (Some('0'), Some('o')) => Some(Base::Octal),
+            (Some('0'), Some('x')) => Some(Base::Hexadecimal),
+            _ => None,
+        };
+        let base = match prefixed {
+            Some(base) => {
+                self.adv();
+                self.adv();
+                if self.read_digits(base) == 0 {
+                    self.error(NoDigits, start);
+                }
+                base
+            }
+            None => {
+                self.read_digits(Base::Decimal);
+                Base::Decimal
+            }
+        };
+
+        // Tuple fields, e.g. `x.0.1`, are always integers
+        let mut float = false;
+        if base == Base::Decimal && !self.after_dot {
+            // The dot belongs to the literal unless it is a range `1..2` or a field `1.foo`
+            if self.cur == Some('.') {
+                match self.peek() {
+                    Some('.') => {}
+                    Some(c) if is_ident_start(c) => {}
+                    _ => {
+                        float = true;
+                        self.adv();
+                        if self.cur.is_some_and(|c| c.is_ascii_digit()) {
+                            self.read_digits(Base::Decimal);
+                        }
+                    }
+                }
+            }
+            // No suffix starts with `e`, so like in rustc it always starts an exponent
+            if self.cur == Some('e') || self.cur == Some('E') {
+                float = true;
+                let exponent = self.pos;
+                self.adv();
+                if self.cur == Some('+') || self.cur == Some('-') {
+                    self.adv();
+                }
+                if self.read_digits(Base::Decimal) == 0 {
+                    self.error(EmptyExponent, exponent);
+                }
+            }
+        }
+
+        // === Suffixes ===
+        let suffix_start = self.pos;
+        if self.cur.is_some_and(is_ident_start) {
+            self.skip_ident_chars();
+        }
+        let suffix = self.source.text(suffix_start.offset, self.pos.offset);
+        if suffix.is_empty() {
+            return if float {
+                Token::LiteralFloat { suffix: None }
+            } else {
+                Token::LiteralInt { base, suffix: None }
+            };
+        }
+        match FloatSuffix::from_name(suffix) {
+            // Decimal integers with a float suffix are floats, e.g. `1f32`
+            Some(suffix) if float || base == Base::Decimal => {
+                return Token::LiteralFloat {
+                    suffix: Some(suffix),
+                };
+            }
+            _ => {}
+        }
+        let int_suffix = IntSuffix::from_name(suffix);
+        if float || int_suffix.is_none() {
+            self.error(InvalidSuffix, suffix_start);
+        }
+        if float {
+            Token::LiteralFloat { suffix: None }
+        } else {
+            Token::LiteralInt {
+                base,
+                suffix: int_suffix,
+            }
+        }
+    }
+
+    /// Read up to `max` hexadecimal digits, ignoring underscores if they are allowed
+    ///
+    /// Returns the value of the digits and their number.
+    fn read_hex_digits(&mut self, max: usize, underscores: bool) -> (u32, usize) {
+        let mut value = 0u32;
+        let mut count = 0;
+        while let Some(c) = self.cur {
+            match c.to_digit(16) {
+                Some(digit) if count < max => {
+                    value = value * 16 + digit;
+                    count += 1;
+                }
+                None if c == '_' && underscores && count > 0 => {}
+                _ => break,
+            }
+            self.adv();
+        }
+        (value, count)
+    }
+
+    /// Tries to read a char from the stream as it would be in literals
+    ///
+    /// `delimiter` is the quote of the literal and `kind` restricts the allowed characters, char
+    /// and byte literals use `StrKind::Str` and `StrKind::Byte` respectively.
+    /// Malformed characters are reported as errors and skipped, except line breaks in char
+    /// literals.
+    fn read_char(&mut self, delimiter: char, kind: StrKind) {
+        use self::LexErrorKind::*;
+
+        let start = self.pos;
+        match self.cur {
+            Some(c) if delimiter == '\'' && (c == '\t' || c == '\r' || c == '\'') => {
+                self.adv();
+                self.error(UnescapedChar(c), start);
+            }
+            Some('\r') => {
+                if self.next() == Some('\n') {
+                    self.adv();
+                } else {
+                    self.error(UnescapedChar('\r'), start);
+                }
+            }
+            Some('\\') => match self.next() {
+                Some('n') | Some('r') | Some('t') | Some('\\') | Some('\'') | Some('"') => {
+                    self.adv()
+                }
+                Some('0') => {
+                    self.adv();
+                    if kind == StrKind::C {
+                        self.error(NulInCStr, start);
+                    }
+                }
+                Some('u') => {
+                    if self.next() != Some('{') {
+                        return self.error(InvalidEscape, start);
+                    }
+                    self.adv();
+                    let (value, count) = self.read_hex_digits(6, true);
+                    if self.cur != Some('}') {
+                        return self.error(InvalidEscape, start);
+                    }
+                    self.adv();
+                    if kind == StrKind::Byte {
+                        self.error(UnicodeEscapeInByte, start);
+                    } else if count == 0 || ::std::char::from_u32(value).is_none() {
+                        self.error(InvalidEscape, start);
+                    } else if kind == StrKind::C && value == 0 {
+                        self.error(NulInCStr, start);
+                    }
+                }
+                Some('x') => {
+                    self.adv();
+                    let (value, count) = self.read_hex_digits(2, false);
+                    if count != 2 || (kind == StrKind::Str && value > 0x7f) {
+                        self.error(InvalidEscape, start);
+                    } else if kind == StrKind::C && value == 0 {
+                        self.error(NulInCStr, start);
+                    }
+                }
+                // Line continuations skip the line break and the indentation of the next line
+                Some('\n') if delimiter == '"' => self.skip_whitespace(),
+                Some('\r') if delimiter == '"' && self.peek() == Some('\n') => {
+                    self.skip_whitespace()
+                }
+                // Char literals end at a line break, the unterminated literal is reported by the
+                // caller
+                Some('\n') | None => {}
+                Some(c) => {
+                    self.adv();
+                    self.error(UnknownEscape(c), start);
+                }
+            },
+            Some(c) => {
+                self.adv();
+                self.check_char(c, kind, start);
+            }
+            None => {}
+        }
+    }
+
+    /// Report a character which is not allowed unescaped in literals of the given kind
+    fn check_char(&mut self, c: char, kind: StrKind, start: Position) {
+        if kind == StrKind::Byte && !c.is_ascii() {
+            self.error(LexErrorKind::NonAsciiByte(c), start);
+        } else if kind == StrKind::C && c == '\0' {
+            self.error(LexErrorKind::NulInCStr, start);
+        }
+    }
+
+    /// Read the rest of a char or byte literal after the opening quote
+    fn read_char_literal(&mut self, start: Position, kind: StrKind) {
+        use self::LexErrorKind::*;
+
+        match self.cur {
+            Some('\'') => {
+                self.adv();
+                self.error(EmptyChar, start);
+            }
+            // Char literals don't continue on the next line, so that lexing a source line by
+            // line gives the same tokens
+            Some('\n') | None => self.error(UnterminatedChar, start),
+            Some(_) => {
+                self.read_char('\'', kind);
+                if self.cur == Some('\'') {
+                    self.adv();
+                    return;
+                }
+                // Either there are more codepoints before the closing quote on this line,
+                // or the literal is not terminated at all
+                while self.cur.is_some() && self.cur != Some('\'') && self.cur != Some('\n') {
+                    self.read_char('\'', kind);
+                }
+                if self.cur == Some('\'') {
+                    self.adv();
+                    self.error(MultiCodepointChar, start);
+                } else {
+                    self.error(UnterminatedChar, start);
+                }
+            }
+        }
+    }
+
+    /// Report a literal or a comment which is not closed at the end of the source, unless it
+    /// continues in the next part
+    fn unterminated(&mut self, kind: LexErrorKind, start: Position, state: LexState) {
+        if self.partial {
+            self.open = Some(state);
+        } else {
+            self.error(kind, start);
+        }
+    }
+
+    /// Read a string literal starting with the opening quote
+    fn read_str(&mut self, start: Position, kind: StrKind) {
+        self.adv();
+        self.read_str_rest(start, kind);
+    }
+
+    /// Read the rest of a string literal after the opening quote
+    fn read_str_rest(&mut self, start: Position, kind: StrKind) {
+        loop {
+            match self.cur {
+                Some('"') => {
+                    self.adv();
+                    break;
+                }
+                Some(_) => {
+                    if !self.skip_bytes(|i| i != b'"' && i != b'\\' && plain_byte(i, kind)) {
+                        self.read_char('"', kind);
+                    }
+                }
+                None => {
+                    let state = LexState::Str { kind, raw: None };
+                    self.unterminated(LexErrorKind::UnterminatedStr, start, state);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Read a raw string literal starting with the `#` symbols or the opening quote
+    ///
+    /// Returns the number of `#` symbols delimiting the string.
+    fn read_raw_str(&mut self, start: Position, kind: StrKind) -> u8 {
+        use self::LexErrorKind::*;
+
+        let mut hashes = 0usize;
+        while self.cur == Some('#') {
+            hashes += 1;
+            self.adv();
+        }
+        if hashes > usize::from(u8::MAX) {
+            self.error(TooManyHashes, start);
+        }
+        match self.cur {
+            Some('"') => self.adv(),
+            Some(c) => {
+                self.error(InvalidRawStrDelimiter(c), start);
+                return hashes as u8;
+            }
+            None => {
+                self.error(UnterminatedStr, start);
+                return hashes as u8;
+            }
+        }
+        let hashes = hashes.min(usize::from(u8::MAX)) as u8;
+        self.read_raw_str_rest(start, kind, hashes);
+        hashes
+    }
+
+    /// Read the rest of a raw string literal after the opening quote
+    fn read_raw_str_rest(&mut self, start: Position, kind: StrKind, hashes: u8) {
+        use self::LexErrorKind::*;
+
+        loop {
+            let char_start = self.pos;
+            match self.cur {
+                Some('"') => {
+                    self.adv();
+                    let mut closing = 0;
+                    while closing < hashes && self.cur == Some('#') {
+                        closing += 1;
+                        self.adv();
+                    }
+                    if closing == hashes {
+                        break;
+                    }
+                }
+                Some('\r') => {
+                    if self.next() != Some('\n') {
+                        self.error(UnescapedChar('\r'), char_start);
+                    }
+                }
+                Some(c) => {
+                    if !self.skip_bytes(|i| i != b'"' && plain_byte(i, kind)) {
+                        self.adv();
+                        self.check_char(c, kind, char_start);
+                    }
+                }
+                None => {
+                    let state = LexState::Str {
+                        kind,
+                        raw: Some(hashes),
+                    };
+                    self.unterminated(UnterminatedStr, start, state);
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Read the rest of a block comment after its opening `/*`
+    ///
+    /// Block comments can be nested, `nested` holds the starts of the unclosed inner ones.
+    fn read_block_comment_rest(
+        &mut self,
+        start: Position,
+        mut nested: Vec<Position>,
+        style: Option<DocStyle>,
+    ) {
+        loop {
+            let comment_start = self.pos;
+            match self.cur {
+                Some('*') => {
+                    if self.next() == Some('/') {
+                        self.adv();
+                        if nested.pop().is_none() {
+                            break;
+                        }
+                    }
+                }
+                Some('/') => {
+                    if self.next() == Some('*') {
+                        self.adv();
+                        nested.push(comment_start);
+                    }
+                }
+                Some(_) => {
+                    self.skip_bytes(|i| i != b'*' && i != b'/');
+                }
+                None => {
+                    let last_nested = nested.last().cloned();
+                    let state = LexState::BlockComment {
+                        depth: nested.len() + 1,
+                        style,
+                    };
+                    self.unterminated(
+                        LexErrorKind::UnterminatedBlockComment { last_nested },
+                        start,
+                        state,
+                    );
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Read the rest of a literal or a comment the source starts inside of
+    fn read_continued(&mut self, start: Position, state: LexState) -> Token {
+        match state {
+            LexState::BlockComment { depth, style } => {
+                // Starts of the outer comments are in the previous parts
+                let nested = vec![start; depth.saturating_sub(1)];
+                self.read_block_comment_rest(start, nested, style);
+                comment(style, CommentKind::Block)
+            }
+            LexState::Str { kind, raw } => {
+                match raw {
+                    Some(hashes) => self.read_raw_str_rest(start, kind, hashes),
+                    None => self.read_str_rest(start, kind),
+                }
+                Token::LiteralStr { kind, raw }
+            }
+            LexState::Start | LexState::Normal { .. } => unreachable!("nothing to continue"),
+        }
+    }
+
+    /// Read an identifier or a keyword
+    fn read_identifier(&mut self, start: Position) -> Token {
+        self.skip_ident_chars();
+        let name = self.source.text(start.offset, self.pos.offset);
+        if name == "_" {
+            return Token::Underscore;
+        }
+        match Keyword::from_name(name) {
+            Some(keyword) if keyword.is_reserved(self.edition) => Token::Keyword(keyword),
+            _ => Token::Identifier,
+        }
+    }
+
+    /// Read the name of a raw identifier after the `r#` prefix
+    fn read_raw_identifier(&mut self, start: Position) {
+        let name_start = self.pos.offset;
+        self.skip_ident_chars();
+        let name = self.source.text(name_start, self.pos.offset);
+        let name = match Keyword::from_name(name) {
+            Some(keyword) if !keyword.can_be_raw() => keyword.as_str(),
+            None if name == "_" => "_",
+            _ => return,
+        };
+        self.error(LexErrorKind::InvalidRawIdentifier(name), start);
+    }
+
+    /// Read a literal with a prefix, e.g. `b'x'`, `c"str"` or `r#"raw"#`, or a raw identifier
+    ///
+    /// Returns `None` if the current character does not start a prefixed literal.
+    /// The prefix itself could be consumed, in which case it is an identifier.
+    fn read_prefixed_literal(&mut self, start: Position) -> Option<Token> {
+        let kind = match self.cur {
+            Some('r') => StrKind::Str,
+            Some('b') => StrKind::Byte,
+            // C strings were introduced in 2021
+            Some('c') if self.edition >= Edition::Edition2021 => StrKind::C,
+            _ => return None,
+        };
+        match (self.cur, self.peek()) {
+            (Some('b'), Some('\'')) => {
+                self.adv();
+                self.adv();
+                self.read_char_literal(start, kind);
+                return Some(Token::LiteralByte);
+            }
+            (Some('b'), Some('"')) | (Some('c'), Some('"')) => {
+                self.adv();
+                self.read_str(start, kind);
+                return Some(Token::LiteralStr { kind, raw: None });
+            }
+            (Some('r'), Some('"')) | (Some('r'), Some('#')) => self.adv(),
+            (Some('b'), Some('r')) | (Some('c'), Some('r')) => {
+                self.adv();
+                match self.peek() {
+                    Some('"') | Some('#') => self.adv(),
+                    // Identifier starting with the prefix, e.g. `break`
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        }
+        // At this point the prefix of a raw string is consumed
+        if self.cur == Some('#') {
+            match self.peek() {
+                Some('"') | Some('#') => {}
+                Some(c) if kind == StrKind::Str && is_ident_start(c) => {
+                    self.adv();
+                    self.read_raw_identifier(start);
+                    return Some(Token::Identifier);
+                }
+                // The prefix is an identifier followed by `#`
+                _ => return Some(Token::Identifier),
+            }
+        }
+        let hashes = self.read_raw_str(start, kind);
+        Some(Token::LiteralStr {
+            kind,
+            raw: Some(hashes),
+        })
+    }
+
+    /// Read the token starting at the current character
+    ///
+    /// Returns `None` if no token could be read, in which case an error is reported.
+    fn read_token(&mut self, start: Position) -> Option<Token> {
+        use self::BinaryOperator::*;
+        use self::LexErrorKind::*;
+        use self::PairedToken::*;
+        use self::Token::*;
+
+        macro_rules! consume {
+            ($token: expr) => {{
+                self.adv();
+                $token
+            }};
+        }
+
+        let cur = self.cur?;
+
+        // === Binary operators ===
+        if let Some(binop) = char_to_binop(cur) {
+            return Some(match (binop, self.next()) {
+                (_, Some('=')) => consume!(BinaryOperatorAssignment(binop)),
+                (And, Some('&')) => consume!(DoubleAnd),
+                (Or, Some('|')) => consume!(DoubleOr),
+                _ => BinaryOperator(binop),
+            });
+        }
+        // === Numerical literals ===
+        if cur.is_ascii_digit() {
+            return Some(self.read_number(start));
+        }
+        // === Identifiers ===
+        if is_ident_start(cur) {
+            if let Some(token) = self.read_prefixed_literal(start) {
+                return Some(token);
+            }
+            return Some(self.read_identifier(start));
+        }
+
+        Some(match cur {
+            // === Special case for operators ===
+            // --- Needed to handle comments
+            '/' => {
+                match self.next() {
+                    Some('=') => consume!(BinaryOperatorAssignment(Slash)),
+                    // Block comments
+                    Some('*') => {
+                        self.adv();
+                        // `/**/` and `/*** ... */` are not doc comments
+                        let style = match (self.cur, self.peek()) {
+                            (Some('*'), Some('*')) | (Some('*'), Some('/')) => None,
+                            (Some('*'), _) => Some(DocStyle::Outer),
+                            (Some('!'), _) => Some(DocStyle::Inner),
+                            _ => None,
+                        };
+                        self.read_block_comment_rest(start, Vec::new(), style);
+                        comment(style, CommentKind::Block)
+                    }
+                    // Line comments
+                    Some('/') => {
+                        self.adv();
+                        // `//// ...` is not a doc comment
+                        let style = match (self.cur, self.peek()) {
+                            (Some('/'), Some('/')) => None,
+                            (Some('/'), _) => Some(DocStyle::Outer),
+                            (Some('!'), _) => Some(DocStyle::Inner),
+                            _ => None,
+                        };
+                        self.skip_line();
+                        comment(style, CommentKind::Line)
+                    }
+                    _ => BinaryOperator(Slash),
+                }
+            }
+            // --- Needed to handle right arrows
+            '-' => match self.next() {
+                Some('=') => consume!(BinaryOperatorAssignment(Minus)),
+                Some('>') => consume!(RightArrow),
+                _ => BinaryOperator(Minus),
+            },
+            // === Structurals ===
+            ',' => consume!(Comma),
+            ';' => consume!(Semicolon),
+            '?' => consume!(Question),
+            '$' => consume!(Dollar),
+            '@' => consume!(At),
+            '~' => consume!(Tilde),
+            '#' => match self.next() {
+                // `#!` at the very start is a shebang, unless it starts an inner attribute
+                Some('!') if start.offset == 0 && self.whole && !self.is_inner_attribute() => {
+                    self.skip_line();
+                    Shebang
+                }
+                _ => Sharp,
+            },
+            '!' => match self.next() {
+                Some('=') => consume!(NotEqual),
+                _ => Exclamation,
+            },
+            ':' => match self.next() {
+                Some(':') => consume!(DoubleColon),
+                _ => Colon,
+            },
+            '.' => {
+                match self.next() {
+                    Some('.') => match self.next() {
+                        Some('.') => consume!(DotDotDot),
+                        Some('=') => consume!(DotDotEq),
+                        _ => DotDot,
+                    },
+                    //Some('=') => consume!(DotEq), // This token should never occur in real code
+                    _ => Dot,
+                }
+            }
+            // === Lifetimes and character literals ===
+            '\'' => match self.next() {
+                // The first symbol could be the start of lifetime,
+                // unless the next symbol is a single quote and it is a char literal
+                Some(c) if is_ident_start(c) && self.peek() != Some('\'') => {
+                    self.skip_ident_chars();
+                    // Lifetimes can't have a closing quote at the end
+                    // The user could mistakenly try to create a char literal with multiple codepoints
+                    if self.cur == Some('\'') {
+                        self.adv();
+                        self.error(MultiCodepointChar, start);
+                        LiteralChar
+                    } else {
+                        IdentifierLifetime
+                    }
+                }
+                _ => {
+                    self.read_char_literal(start, StrKind::Str);
+                    LiteralChar
+                }
+            },
+            // === Paired tokens ===
+            '(' => consume!(Left(Parenthesis)),
+            ')' => consume!(Right(Parenthesis)),
+            '{' => consume!(Left(Brace)),
+            '}' => consume!(Right(Brace)),
+            '[' => consume!(Left(Bracket)),
+            ']' => consume!(Right(Bracket)),
+            // === String literals ===
+            '"' => {
+                self.read_str(start, StrKind::Str);
+                LiteralStr {
+                    kind: StrKind::Str,
+                    raw: None,
+                }
+            }
+            // === Comparison operators and assignment ===
+            '<' => match self.next() {
+                Some('=') => consume!(LessEqual),
+                Some('-') => consume!(LeftArrow),
+                Some('<') => match self.next() {
+                    Some('=') => consume!(BinaryOperatorAssignment(Shl)),
+                    _ => BinaryOperator(Shl),
+                },
+                _ => LessThan,
+            },
+            '>' => match self.next() {
+                Some('=') => consume!(GreaterEqual),
+                Some('>') => match self.next() {
+                    Some('=') => consume!(BinaryOperatorAssignment(Shr)),
+                    _ => BinaryOperator(Shr),
+                },
+                _ => GreaterThan,
+            },
+            '=' => match self.next() {
+                Some('=') => consume!(DoubleEqual),
+                Some('>') => consume!(FatArrow),
+                _ => Equal,
+            },
+            _ if cur.is_ascii_whitespace() => {
+                self.skip_whitespace();
+                Whitespace
+            }
+            _ => {
+                self.adv();
+                self.error(UnexpectedChar(cur), start);
+                if !self.lossless {
+                    return None;
+                }
+                Unknown
+            }
+        })
+    }
+}
+
+/// Stream of tokens build from the iterator of characters
+///
+/// Malformed input does not stop the tokenizer. Errors found inside of a token, e.g. a bad escape
+/// sequence in a string literal, are yielded right before the token itself, which is still
+/// produced. Characters which can't start any token are reported and skipped.
+pub struct Tokenizer<S: Iterator> {
+    lexer: Lexer<CharSource<S>>,
+    /// Whether texts of identifiers are converted to the Normalization Form C
+    nfc: bool,
+}
+
+impl<S> Tokenizer<S>
+where
+    S: Iterator<Item = char>,
+{
+    pub fn new(iter: S) -> Self {
+        Tokenizer {
+            lexer: Lexer::new(CharSource {
+                iter,
+                lookahead: VecDeque::new(),
+                buf: String::new(),
+                start: 0,
+            }),
+            nfc: false,
+        }
+    }
+
+    /// Set the edition of the source, which defines its keywords and literals
+    pub fn with_edition(mut self, edition: Edition) -> Self {
+        self.lexer.edition = edition;
+        self
+    }
+
+    /// Convert texts of identifiers and lifetimes to the Normalization Form C, so that the same
+    /// names written with different sequences of codepoints have the same symbol
+    ///
+    /// Spans still refer to the original text. Has no effect in the lossless mode.
+    pub fn with_nfc_identifiers(mut self, normalize: bool) -> Self {
+        self.nfc = normalize;
+        self
+    }
+
+    /// Produce a token for every character of the source, so that concatenating the texts of
+    /// all tokens reproduces the source exactly
+    ///
+    /// Characters which can't start any token are still reported, but they are also yielded as
+    /// `Unknown` tokens after the errors. Texts of identifiers are never normalized.
+    pub fn with_lossless(mut self, lossless: bool) -> Self {
+        self.lexer.lossless = lossless;
+        self
+    }
+
+    /// Lex a part of a larger source, e.g. a line, which starts in the given state
+    ///
+    /// Literals and comments left open at the end of the part are not reported as unterminated,
+    /// the state to lex the next part in is available from [`Tokenizer::state`]. Positions are
+    /// relative to the start of the part.
+    pub fn with_state(mut self, state: LexState) -> Self {
+        self.lexer.set_state(state);
+        self
+    }
+
+    /// State of the lexer after the tokens read so far
+    pub fn state(&self) -> LexState {
+        self.lexer.state()
+    }
+
+    /// Turn the tokenizer into a stream of tokens with their locations
+    pub fn spanned(self) -> Spanned<S> {
+        Spanned(self)
+    }
+
+    /// Iterator of characters the tokenizer reads from
+    pub fn get_ref(&self) -> &S {
+        &self.lexer.source.iter
+    }
+
+    /// Continue lexing in the middle of a source, the characters start at the position right
+    /// after the token, which was `.` if `after_dot` is set
+    pub(crate) fn resume_at(mut self, pos: Position, after_dot: bool) -> Self {
+        self.lexer.pos = pos;
+        self.lexer.after_dot = after_dot;
+        self
+    }
+
+    /// Position of the next character, which is the start of the next token once all the
+    /// pending ones were yielded
+    pub(crate) fn position(&self) -> Position {
+        self.lexer.pos
+    }
+
+    /// Retrieve the next token of incoming source code with its location
+    pub fn next_spanned(&mut self) -> Option<Result<SpannedToken, LexError>> {
+        let lexed = match self.lexer.next_token()? {
+            Ok(lexed) => lexed,
+            Err(error) => return Some(Err(error)),
+        };
+        let text = self
+            .lexer
+            .source
+            .text(lexed.span.start.offset, lexed.span.end.offset);
+        let normalize = self.nfc
+            && !self.lexer.lossless
+            && (lexed.token == Token::Identifier || lexed.token == Token::IdentifierLifetime)
+            && !text.is_ascii();
+        let text = if normalize {
+            Symbol::intern(&unicode::to_nfc(text))
+        } else {
+            Symbol::intern(text)
+        };
+        Some(Ok(SpannedToken {
+            token: lexed.token,
+            span: lexed.span,
+            text,
+            spacing: lexed.spacing,
+        }))
+    }
+
+    /// Retrieve the next token with its text borrowed from the tokenizer
+    ///
+    /// Unlike the interned texts of [`Tokenizer::next_spanned`], the text is only kept until the
+    /// next token is read, so memory used by the tokenizer does not grow with the size of the
+    /// source. Identifiers are not normalized.
+    pub fn next_lexeme(&mut self) -> Option<Result<Lexeme<'_>, LexError>> {
+        let lexed = match self.lexer.next_token()? {
+            Ok(lexed) => lexed,
+            Err(error) => return Some(Err(error)),
+        };
+        Some(Ok(Lexeme {
+            token: lexed.token,
+            span: lexed.span,
+            text: self
+                .lexer
+                .source
+                .text(lexed.span.start.offset, lexed.span.end.offset),
+            spacing: lexed.spacing,
+        }))
+    }
+}
+
+impl<R: BufRead> Tokenizer<ReadChars<R>> {
+    /// Read the source from the reader, decoding it incrementally
+    ///
+    /// The reading stops at the first I/O error or invalid UTF-8, which is then available from
+    /// [`ReadChars::error`] through [`Tokenizer::get_ref`]. Offsets of tokens are in bytes of
+    /// the read data.
+    ///
+    /// Only the text of the current token is kept in memory when iterating over the tokens or
+    /// reading them with [`Tokenizer::next_lexeme`], while [`Tokenizer::next_spanned`] interns
+    /// the texts of all of them.
+    pub fn from_reader(reader: R) -> Self {
+        Tokenizer::new(ReadChars::new(reader))
+    }
+}
+
+impl<S> Iterator for Tokenizer<S>
+where
+    S: Iterator<Item = char>,
+{
+    type Item = Result<Token, LexError>;
+
+    /// Retrieve the next token of incoming source code
+    ///
+    /// The text of the token is not interned, so the iteration uses bounded memory.
+    fn next(&mut self) -> Option<Result<Token, LexError>> {
+        self.next_lexeme().map(|i| i.map(|i| i.token))
+    }
+}
+
+/// Stream of tokens with their locations, see [`Tokenizer::spanned`]
+pub struct Spanned<S: Iterator>(Tokenizer<S>);
+
+impl<S> Iterator for Spanned<S>
+where
+    S: Iterator<Item = char>,
+{
+    type Item = Result<SpannedToken, LexError>;
+
+    fn next(&mut self) -> Option<Result<SpannedToken, LexError>> {
+        self.0.next_spanned()
+    }
+}
+
+/// Stream of tokens scanning a string in memory, with their texts borrowed from it
+///
+/// Produces the same tokens and errors as the [`Tokenizer`], but about twice as fast, see
+/// `cargo bench`: runs of ASCII identifier characters, whitespace and digits, and the contents of
+/// strings and comments are skipped by scanning the bytes of the source, and the texts of tokens
+/// are slices of it rather than copied from the characters of an iterator and interned.
+/// Identifiers are not normalized, the texts are always the exact slices.
+pub struct StrTokenizer<'a> {
+    lexer: Lexer<StrSource<'a>>,
+}
+
+impl<'a> StrTokenizer<'a> {
+    pub fn new(src: &'a str) -> Self {
+        StrTokenizer {
+            lexer: Lexer::new(StrSource { src, offset: 0 }),
+        }
+    }
+
+    /// Scan the bytes of a source, which must be valid UTF-8
+    pub fn from_bytes(src: &'a [u8]) -> Result<Self, Utf8Error> {
+        Ok(Self::new(str::from_utf8(src)?))
+    }
+
+    /// Set the edition of the source, which defines its keywords and literals
+    pub fn with_edition(mut self, edition: Edition) -> Self {
+        self.lexer.edition = edition;
+        self
+    }
+
+    /// Produce a token for every character of the source, see [`Tokenizer::with_lossless`]
+    pub fn with_lossless(mut self, lossless: bool) -> Self {
+        self.lexer.lossless = lossless;
+        self
+    }
+
+    /// Lex a part of a larger source, which starts in the given state, see
+    /// [`Tokenizer::with_state`]
+    pub fn with_state(mut self, state: LexState) -> Self {
+        self.lexer.set_state(state);
+        self
+    }
+
+    /// State of the lexer after the tokens read so far
+    pub fn state(&self) -> LexState {
+        self.lexer.state()
+    }
+}
+
+impl<'a> Iterator for StrTokenizer<'a> {
+    type Item = Result<Lexeme<'a>, LexError>;
+
+    fn next(&mut self) -> Option<Result<Lexeme<'a>, LexError>> {
+        let src = self.lexer.source.src;
+        self.lexer.next_token().map(|i| {
+            i.map(|lexed| Lexeme {
+                token: lexed.token,
+                span: lexed.span,
+                text: &src[lexed.span.start.offset..lexed.span.end.offset],
+                spacing: lexed.spacing,
+            })
+        })
+    }
+}
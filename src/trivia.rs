@@ -0,0 +1,114 @@
+//! Attachment of trivia (whitespace, comments and the shebang) to significant tokens
+//!
+//! Together with the lossless mode of the tokenizer this gives a concrete token stream,
+//! from which the source can be reproduced byte-for-byte, e.g. after editing some of the tokens.
+//!
+//! Trivia after a token up to the first line break, i.e. up to the next significant token or
+//! the first trivia token containing a line break, is trailing trivia of this token.
+//! The rest of trivia before a token is its leading trivia. Trivia after the last token is kept
+//! separately.
+
+use token::{Span, SpannedToken};
+use tokenizer::LexError;
+
+/// Significant token with the trivia around it
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TriviaToken {
+    /// Trivia from the end of the previous line up to the token
+    pub leading: Vec<SpannedToken>,
+    pub token: SpannedToken,
+    /// Trivia after the token up to the end of its line
+    pub trailing: Vec<SpannedToken>,
+}
+
+impl TriviaToken {
+    /// Region of the source covered by the token together with its trivia
+    pub fn full_span(&self) -> Span {
+        Span {
+            start: self.leading.first().unwrap_or(&self.token).span.start,
+            end: self.trailing.last().unwrap_or(&self.token).span.end,
+        }
+    }
+
+    /// All the tokens in the order of the source
+    pub fn iter(&self) -> impl Iterator<Item = &SpannedToken> {
+        self.leading
+            .iter()
+            .chain(Some(&self.token))
+            .chain(self.trailing.iter())
+    }
+}
+
+/// Significant tokens of a source with all the trivia attached
+#[derive(Clone, Eq, PartialEq, Debug, Default)]
+pub struct TriviaStream {
+    pub tokens: Vec<TriviaToken>,
+    /// Trivia after the last significant token, or all trivia if there are no such tokens
+    pub end: Vec<SpannedToken>,
+}
+
+impl TriviaStream {
+    /// All the tokens in the order of the source
+    pub fn iter(&self) -> impl Iterator<Item = &SpannedToken> {
+        self.tokens
+            .iter()
+            .flat_map(TriviaToken::iter)
+            .chain(self.end.iter())
+    }
+
+    /// Concatenated texts of all the tokens, i.e. the source if the stream is lossless
+    pub fn text(&self) -> String {
+        self.iter().map(|i| i.text.as_str()).collect()
+    }
+}
+
+/// Attach the trivia to significant tokens, collecting the errors separately
+pub fn attach<I>(tokens: I) -> (TriviaStream, Vec<LexError>)
+where
+    I: IntoIterator<Item = Result<SpannedToken, LexError>>,
+{
+    let mut stream = TriviaStream::default();
+    let mut errors = Vec::new();
+    // Trivia after the last significant token, which is not yet known to be leading or trailing
+    let mut trivia = Vec::new();
+    // Whether a line break was found after the last significant token
+    let mut line_break = false;
+
+    for token in tokens {
+        let token = match token {
+            Ok(token) => token,
+            Err(error) => {
+                errors.push(error);
+                continue;
+            }
+        };
+        if token.token.is_trivia() {
+            if !line_break && token.text.as_str().contains('\n') {
+                line_break = true;
+                if let Some(last) = stream.tokens.last_mut() {
+                    last.trailing.append(&mut trivia);
+                }
+            }
+            trivia.push(token);
+        } else {
+            if !line_break {
+                if let Some(last) = stream.tokens.last_mut() {
+                    last.trailing.append(&mut trivia);
+                }
+            }
+            line_break = false;
+            stream.tokens.push(TriviaToken {
+                leading: trivia.split_off(0),
+                token,
+                trailing: Vec::new(),
+            });
+        }
+    }
+    if !line_break {
+        if let Some(last) = stream.tokens.last_mut() {
+            last.trailing.append(&mut trivia);
+        }
+    }
+    stream.end = trivia;
+    (stream, errors)
+}
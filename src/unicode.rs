@@ -0,0 +1,160 @@
+//! Unicode properties needed for lexing identifiers
+//!
+//! Identifiers follow Unicode Standard Annex #31, see
+//! <https://doc.rust-lang.org/reference/identifiers.html>
+//! All the data is embedded in the crate, see `scripts/unicode.py`.
+
+use std::cmp::Ordering;
+
+use unicode_tables::{COMBINING_CLASSES, COMPOSITIONS, DECOMPOSITIONS, XID_CONTINUE, XID_START};
+
+// Hangul syllables are composed and decomposed algorithmically
+const HANGUL_S_BASE: u32 = 0xAC00;
+const HANGUL_L_BASE: u32 = 0x1100;
+const HANGUL_V_BASE: u32 = 0x1161;
+const HANGUL_T_BASE: u32 = 0x11A7;
+const HANGUL_L_COUNT: u32 = 19;
+const HANGUL_V_COUNT: u32 = 21;
+const HANGUL_T_COUNT: u32 = 28;
+const HANGUL_N_COUNT: u32 = HANGUL_V_COUNT * HANGUL_T_COUNT;
+const HANGUL_S_COUNT: u32 = HANGUL_L_COUNT * HANGUL_N_COUNT;
+
+/// Whether the character is in one of the sorted ranges
+fn in_ranges(c: char, ranges: &[(char, char)]) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if c < start {
+                Ordering::Greater
+            } else if c > end {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// This character has the `XID_Start` property and can start an identifier
+pub fn is_xid_start(c: char) -> bool {
+    in_ranges(c, XID_START)
+}
+
+/// This character has the `XID_Continue` property and can continue an identifier
+pub fn is_xid_continue(c: char) -> bool {
+    in_ranges(c, XID_CONTINUE)
+}
+
+/// Canonical combining class of the character, `0` for starters
+fn combining_class(c: char) -> u8 {
+    COMBINING_CLASSES
+        .binary_search_by(|&(start, end, _)| {
+            if c < start {
+                Ordering::Greater
+            } else if c > end {
+                Ordering::Less
+            } else {
+                Ordering::Equal
+            }
+        })
+        .map(|i| COMBINING_CLASSES[i].2)
+        .unwrap_or(0)
+}
+
+/// Push the full canonical decomposition of the character
+fn decompose(c: char, out: &mut Vec<char>) {
+    let code = c as u32;
+    if (HANGUL_S_BASE..HANGUL_S_BASE + HANGUL_S_COUNT).contains(&code) {
+        let index = code - HANGUL_S_BASE;
+        let l = HANGUL_L_BASE + index / HANGUL_N_COUNT;
+        let v = HANGUL_V_BASE + index % HANGUL_N_COUNT / HANGUL_T_COUNT;
+        let t = HANGUL_T_BASE + index % HANGUL_T_COUNT;
+        out.extend(::std::char::from_u32(l));
+        out.extend(::std::char::from_u32(v));
+        if t != HANGUL_T_BASE {
+            out.extend(::std::char::from_u32(t));
+        }
+        return;
+    }
+    match DECOMPOSITIONS.binary_search_by_key(&c, |&(composed, _, _)| composed) {
+        Ok(i) => {
+            let (_, first, second) = DECOMPOSITIONS[i];
+            decompose(first, out);
+            if second != '\0' {
+                decompose(second, out);
+            }
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+/// Find the primary composite of two characters
+fn compose(first: char, second: char) -> Option<char> {
+    let (a, b) = (first as u32, second as u32);
+    if (HANGUL_L_BASE..HANGUL_L_BASE + HANGUL_L_COUNT).contains(&a)
+        && (HANGUL_V_BASE..HANGUL_V_BASE + HANGUL_V_COUNT).contains(&b)
+    {
+        let index = (a - HANGUL_L_BASE) * HANGUL_N_COUNT + (b - HANGUL_V_BASE) * HANGUL_T_COUNT;
+        return ::std::char::from_u32(HANGUL_S_BASE + index);
+    }
+    if (HANGUL_S_BASE..HANGUL_S_BASE + HANGUL_S_COUNT).contains(&a)
+        && (a - HANGUL_S_BASE) % HANGUL_T_COUNT == 0
+        && (HANGUL_T_BASE + 1..HANGUL_T_BASE + HANGUL_T_COUNT).contains(&b)
+    {
+        return ::std::char::from_u32(a + b - HANGUL_T_BASE);
+    }
+    COMPOSITIONS
+        .binary_search_by_key(&(first, second), |&(a, b, _)| (a, b))
+        .ok()
+        .map(|i| COMPOSITIONS[i].2)
+}
+
+/// Convert the string into Unicode Normalization Form C
+///
+/// Identifiers which are the same after the normalization are considered equal by Rust.
+pub fn to_nfc(s: &str) -> String {
+    let mut chars = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        decompose(c, &mut chars);
+    }
+
+    // Canonical ordering of combining marks, the sort is stable
+    let mut start = 0;
+    while start < chars.len() {
+        let end = start
+            + chars[start..]
+                .iter()
+                .take_while(|&&c| combining_class(c) != 0)
+                .count();
+        chars[start..end].sort_by_key(|&c| combining_class(c));
+        start = end + 1;
+    }
+
+    // Canonical composition
+    let mut result: Vec<char> = Vec::with_capacity(chars.len());
+    let mut starter = None;
+    // Combining class of the last character after the starter, `None` if there are none
+    let mut last_class = None;
+    for c in chars {
+        let class = combining_class(c);
+        if let Some(index) = starter {
+            let blocked = match last_class {
+                Some(last) => last >= class,
+                None => false,
+            };
+            if !blocked {
+                if let Some(composite) = compose(result[index], c) {
+                    result[index] = composite;
+                    continue;
+                }
+            }
+        }
+        result.push(c);
+        if class == 0 {
+            starter = Some(result.len() - 1);
+            last_class = None;
+        } else {
+            last_class = Some(class);
+        }
+    }
+    result.into_iter().collect()
+}
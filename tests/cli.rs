@@ -0,0 +1,120 @@
+//! Runs of the program itself, with its exit statuses and outputs
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run the program with the arguments and the given standard input
+fn run(args: &[&str], input: &str) -> (i32, String, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rust-lexer"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to start the program");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = child.wait_with_output().unwrap();
+    (
+        status.code().expect("the program was killed"),
+        String::from_utf8(stdout).unwrap(),
+        String::from_utf8(stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_stdin() {
+    let expected = "Keyword(Let) \"let\"\nWhitespace \" \"\nIdentifier \"x\"\n";
+    assert_eq!(run(&[], "let x"), (0, expected.to_string(), String::new()));
+    assert_eq!(
+        run(&["-"], "let x"),
+        (0, expected.to_string(), String::new())
+    );
+    assert_eq!(
+        run(&["-f", "spans"], "x"),
+        (0, "1:1-1:2 Identifier \"x\"\n".to_string(), String::new())
+    );
+}
+
+#[test]
+fn test_exit_status() {
+    let (status, stdout, stderr) = run(&["--color", "never"], "a ` b");
+    assert_eq!(status, 1);
+    assert_eq!(
+        stdout,
+        "Identifier \"a\"\nWhitespace \" \"\nWhitespace \" \"\nIdentifier \"b\"\n"
+    );
+    assert_eq!(
+        stderr,
+        "error: unknown start of token: `
+ --> <stdin>:1:3
+  |
+1 | a ` b
+  |   ^
+
+"
+    );
+
+    // Errors show the lines of the standard input they point to, even inside multiline tokens
+    let (status, _, stderr) = run(&["--color", "never"], "x\n\"a\\\r\n\\q\r\nb\" `\n");
+    assert_eq!(status, 1);
+    assert_eq!(
+        stderr,
+        "error: unknown character escape: q
+ --> <stdin>:3:1
+  |
+3 | \\q
+  | ^^ unknown character escape
+  |
+  = help: if you meant to write a literal backslash, escape it: `\\\\`
+
+error: unknown start of token: `
+ --> <stdin>:4:4
+  |
+4 | b\" `
+  |    ^
+
+"
+    );
+
+    let (status, stdout, stderr) = run(&["--unknown"], "");
+    assert_eq!((status, stdout.as_str()), (2, ""));
+    assert!(stderr.starts_with("error: unknown option --unknown\n\nUsage: "));
+
+    let (status, _, stderr) = run(&["test/nonexistent.rs", "-"], "x");
+    assert_eq!(status, 2);
+    assert!(stderr.starts_with("test/nonexistent.rs: error: "));
+
+    let (status, stdout, stderr) = run(&["--help"], "");
+    assert_eq!((status, stderr.as_str()), (0, ""));
+    assert!(stdout.starts_with("Usage: rust-lexer [OPTIONS] [PATH]..."));
+}
+
+#[test]
+fn test_directories() {
+    let (status, stdout, stderr) = run(&["test"], "");
+    assert_eq!((status, stderr.as_str()), (0, ""));
+    let headers = stdout
+        .lines()
+        .filter(|i| i.starts_with("==> "))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        headers,
+        [
+            "==> test/collections.rs <==",
+            "==> test/lexer.rs <==",
+            "==> test/mem.rs <==",
+            "==> test/parser.rs <==",
+            "==> test/token.rs <==",
+            "==> test/vec.rs <==",
+        ]
+    );
+}